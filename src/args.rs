@@ -0,0 +1,344 @@
+//! Conversion of Handlebars values into Fluent arguments.
+
+use std::collections::BTreeMap;
+
+use fluent_bundle::types::FluentNumber;
+use fluent_bundle::{FluentArgs, FluentValue};
+use handlebars::{PathAndJson, RenderError, RenderErrorReason};
+use serde_json::{Map, Number, Value as Json};
+
+use crate::types::{parse_offset, FluentDateTime, FluentList};
+
+/// `FluentNumberOptions` that can be set on a numeric argument `foo` with `foo-<option>=…`
+const NUMBER_OPTIONS: &[&str] = &[
+    "type",
+    "style",
+    "currency",
+    "currencyDisplay",
+    "useGrouping",
+    "minimumIntegerDigits",
+    "minimumFractionDigits",
+    "maximumFractionDigits",
+    "minimumSignificantDigits",
+    "maximumSignificantDigits",
+];
+
+/// Options that can be set on an argument `foo` marked as a datetime with `foo-type="datetime"`
+const DATETIME_OPTIONS: &[&str] = &["type", "dateStyle", "timeStyle", "timeZone"];
+
+/// Options that can be set on an array argument `foo`, which is passed as a [`FluentList`]
+const LIST_OPTIONS: &[&str] = &["type", "style", "field"];
+
+/// Hash key whose object value is spread into the arguments, with `args-prefix` before each name
+const SPREAD_KEY: &str = "args";
+
+/// The largest integer magnitude that survives a round trip through `f64`
+pub(crate) const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Convert a number, keeping integers exact.
+///
+/// Integers too large to be represented by a `FluentNumber` are passed as strings
+/// so that they're at least displayed correctly.
+fn number_to_fluent(n: &Number) -> FluentValue<'static> {
+    if let Some(i) = n.as_i64() {
+        if i.unsigned_abs() <= MAX_SAFE_INTEGER {
+            return i.into();
+        }
+    } else if let Some(u) = n.as_u64() {
+        if u <= MAX_SAFE_INTEGER {
+            return u.into();
+        }
+    } else if let Some(f) = n.as_f64() {
+        return f.into();
+    }
+    n.to_string().into()
+}
+
+/// Convert a scalar JSON value, returning `None` for anything Fluent can't represent
+pub(crate) fn json_to_fluent(value: &Json) -> Option<FluentValue<'static>> {
+    match value {
+        Json::Number(n) => Some(number_to_fluent(n)),
+        Json::String(s) => Some(s.to_owned().into()),
+        _ => None,
+    }
+}
+
+/// Parse a number from text, keeping integers exact and the fraction digits of decimals.
+///
+/// Only JSON number syntax is accepted, so that values like `007` or `NaN` stay strings.
+pub(crate) fn parse_number(s: &str) -> Option<FluentValue<'static>> {
+    let s = s.trim();
+    let n: Number = s.parse().ok()?;
+    if n.is_f64() {
+        s.parse::<FluentNumber>().ok().map(FluentValue::Number)
+    } else {
+        Some(number_to_fluent(&n))
+    }
+}
+
+/// Whether a `-type` option asks for the argument to be a number
+fn is_number_type(ty: Option<&Json>) -> bool {
+    matches!(
+        ty.and_then(|t| t.as_str()),
+        Some("number" | "cardinal" | "ordinal")
+    )
+}
+
+/// If `key` is of the form `<name>-<option>` for another hash entry `name`, return both parts
+fn split_option<'a, V>(key: &'a str, hash: &BTreeMap<&str, V>) -> Option<(&'a str, &'a str)> {
+    let (name, option) = key.rsplit_once('-')?;
+    if hash.contains_key(name) {
+        Some((name, option))
+    } else {
+        None
+    }
+}
+
+/// Build Fluent arguments from a helper's hash.
+///
+/// Numeric arguments may be given formatting options, as in
+/// `amount=total amount-style="currency" amount-currency="EUR"`. Arguments marked with
+/// `when-type="datetime"` are converted to a [`FluentDateTime`], displayed at `time_zone`
+/// (in minutes east of UTC) unless overridden with `when-timeZone`.
+///
+/// Strings are converted to numbers when marked with `count-type="number"`, or whenever
+/// they look like one if `coerce` is set.
+///
+/// An object passed as `args=…` is spread into the arguments first, so that other hash
+/// entries override its fields. `reserved` entries, like `lang`, are options of the helper
+/// rather than arguments.
+pub(crate) fn from_hash(
+    hash: &BTreeMap<&str, PathAndJson>,
+    reserved: &[&str],
+    time_zone: Option<i32>,
+    coerce: bool,
+) -> Result<FluentArgs<'static>, RenderError> {
+    let mut args = FluentArgs::new();
+    let spread = match hash.get(SPREAD_KEY).map(|v| v.value()) {
+        Some(Json::Object(ref object)) => {
+            let prefix = match option(hash, SPREAD_KEY, "prefix") {
+                Some(Json::String(ref prefix)) => prefix.as_str(),
+                None => "",
+                Some(_) => {
+                    return Err(RenderErrorReason::ParamTypeMismatchForName(
+                        "fluent",
+                        "args-prefix".into(),
+                        "string".into(),
+                    )
+                    .into())
+                }
+            };
+            spread_object(&mut args, prefix, object, coerce);
+            true
+        }
+        _ => false,
+    };
+    for (k, v) in hash {
+        if reserved.contains(k) || spread && (*k == SPREAD_KEY || *k == "args-prefix") {
+            continue;
+        }
+        if split_option(k, hash).is_some_and(|(_, o)| {
+            NUMBER_OPTIONS.contains(&o)
+                || DATETIME_OPTIONS.contains(&o)
+                || LIST_OPTIONS.contains(&o)
+        }) {
+            continue;
+        }
+        if option(hash, k, "type") == Some(&Json::from("datetime")) {
+            let datetime = to_datetime(k, v.value(), hash, time_zone)?;
+            args.set(k.to_string(), FluentValue::Custom(Box::new(datetime)));
+            continue;
+        }
+        if let Json::Array(ref items) = *v.value() {
+            args.set(
+                k.to_string(),
+                FluentValue::Custom(Box::new(to_list(k, items, hash))),
+            );
+            continue;
+        }
+        let mut value = match *v.value() {
+            Json::String(ref s) if is_number_type(option(hash, k, "type")) => parse_number(s)
+                .ok_or_else(|| {
+                    RenderErrorReason::ParamTypeMismatchForName(
+                        "fluent",
+                        k.to_string(),
+                        "number".into(),
+                    )
+                })?,
+            Json::String(ref s) if coerce => parse_number(s).unwrap_or_else(|| s.clone().into()),
+            ref json => match json_to_fluent(json) {
+                Some(value) => value,
+                None => continue,
+            },
+        };
+        if let FluentValue::Number(ref mut n) = value {
+            apply_number_options(n, k, hash);
+        }
+        args.set(k.to_string(), value);
+    }
+    Ok(args)
+}
+
+/// Add the fields of `object` to `args`, with the names of nested fields joined by `_`
+fn spread_object(
+    args: &mut FluentArgs<'static>,
+    prefix: &str,
+    object: &Map<String, Json>,
+    coerce: bool,
+) {
+    for (key, value) in object {
+        let name = format!("{}{}", prefix, key);
+        if let Json::Object(ref object) = value {
+            spread_object(args, &format!("{}_", name), object, coerce);
+        } else if let Some(value) = value_to_fluent(value, coerce) {
+            args.set(name, value);
+        }
+    }
+}
+
+/// Convert a scalar or an array (as a list with default options), returning `None` for
+/// anything else
+pub(crate) fn value_to_fluent(value: &Json, coerce: bool) -> Option<FluentValue<'static>> {
+    match value {
+        Json::Array(ref items) => {
+            let items = items.iter().filter_map(json_to_fluent).collect();
+            Some(FluentValue::Custom(Box::new(FluentList::new(items))))
+        }
+        Json::String(ref s) if coerce => Some(parse_number(s).unwrap_or_else(|| s.clone().into())),
+        json => json_to_fluent(json),
+    }
+}
+
+/// Noncharacters standing in for the characters of Handlebars syntax in arguments while a
+/// message is rendered as a template. They aren't found in text.
+const PLACEHOLDERS: &[(char, char)] = &[('{', '\u{fdd0}'), ('}', '\u{fdd1}'), ('\\', '\u{fdd2}')];
+
+/// Replace braces and backslashes in string arguments and list items with placeholders, so
+/// that values can't add Handlebars expressions to a message that is rendered as a template,
+/// even next to other arguments or to braces in the message. [`restore_mustaches()`] turns
+/// them back once the template is rendered.
+pub(crate) fn escape_mustaches(args: FluentArgs<'static>) -> FluentArgs<'static> {
+    map_strings(args, &|s| {
+        s.chars()
+            .map(|c| match PLACEHOLDERS.iter().find(|(from, _)| *from == c) {
+                Some(&(_, placeholder)) => placeholder,
+                None => c,
+            })
+            .collect()
+    })
+}
+
+/// Undo the replacements of [`escape_mustaches()`]
+pub(crate) fn restore_mustaches(text: &str) -> String {
+    text.chars()
+        .map(|c| match PLACEHOLDERS.iter().find(|(_, to)| *to == c) {
+            Some(&(original, _)) => original,
+            None => c,
+        })
+        .collect()
+}
+
+/// HTML-escape string arguments and list items, so that values can't add markup to a message
+pub(crate) fn escape_html(args: FluentArgs<'static>) -> FluentArgs<'static> {
+    map_strings(args, &handlebars::html_escape)
+}
+
+/// Apply `f` to string arguments and the strings in list arguments
+fn map_strings(args: FluentArgs<'static>, f: &dyn Fn(&str) -> String) -> FluentArgs<'static> {
+    fn map(value: FluentValue<'static>, f: &dyn Fn(&str) -> String) -> FluentValue<'static> {
+        match value {
+            FluentValue::String(s) => f(&s).into(),
+            FluentValue::Custom(ref c) => match c.as_any().downcast_ref::<FluentList>() {
+                Some(list) => {
+                    let items = list.items().iter().map(|i| map(i.clone(), f)).collect();
+                    let mut mapped = FluentList::new(items);
+                    mapped.options = list.options;
+                    FluentValue::Custom(Box::new(mapped))
+                }
+                None => value,
+            },
+            value => value,
+        }
+    }
+    args.into_iter()
+        .map(|(name, value)| (name, map(value, f)))
+        .collect()
+}
+
+/// Convert an array to a list, picking out `<name>-field` from any objects in it
+fn to_list(name: &str, items: &[Json], hash: &BTreeMap<&str, PathAndJson>) -> FluentList {
+    let field = option(hash, name, "field").and_then(|f| f.as_str());
+    let items = items
+        .iter()
+        .filter_map(|item| match (item, field) {
+            (Json::Object(o), Some(field)) => o.get(field).and_then(json_to_fluent),
+            _ => json_to_fluent(item),
+        })
+        .collect();
+    let mut list = FluentList::new(items);
+    let mut options = FluentArgs::new();
+    for option_name in ["type", "style"] {
+        if let Some(value) = option(hash, name, option_name).and_then(json_to_fluent) {
+            options.set(option_name, value);
+        }
+    }
+    list.options.merge(&options);
+    list
+}
+
+fn option<'a>(hash: &'a BTreeMap<&str, PathAndJson>, name: &str, option: &str) -> Option<&'a Json> {
+    hash.get(&*format!("{}-{}", name, option))
+        .map(|v| v.value())
+}
+
+/// Convert an RFC 3339 string or a Unix timestamp in seconds to a datetime
+fn to_datetime(
+    name: &str,
+    value: &Json,
+    hash: &BTreeMap<&str, PathAndJson>,
+    time_zone: Option<i32>,
+) -> Result<FluentDateTime, RenderError> {
+    let mismatch = |expected: &str| -> RenderError {
+        RenderErrorReason::ParamTypeMismatchForName("fluent", name.into(), expected.into()).into()
+    };
+    let mut datetime = match value {
+        Json::String(s) => FluentDateTime::parse_rfc3339(s),
+        Json::Number(n) => n.as_i64().map(|secs| FluentDateTime::new(secs, 0)),
+        _ => None,
+    }
+    .ok_or_else(|| mismatch("RFC 3339 datetime or Unix timestamp"))?;
+
+    let time_zone = match option(hash, name, "timeZone") {
+        Some(Json::String(zone)) => {
+            Some(parse_offset(zone).ok_or_else(|| mismatch("valid timeZone offset"))?)
+        }
+        _ => time_zone,
+    };
+    if let Some(offset) = time_zone {
+        datetime = datetime.with_offset(offset);
+    }
+
+    let mut options = FluentArgs::new();
+    for style in ["dateStyle", "timeStyle"] {
+        if let Some(value) = option(hash, name, style).and_then(json_to_fluent) {
+            options.set(style, value);
+        }
+    }
+    datetime.options.merge(&options);
+    Ok(datetime)
+}
+
+fn apply_number_options(n: &mut FluentNumber, name: &str, hash: &BTreeMap<&str, PathAndJson>) {
+    let mut options = FluentArgs::new();
+    for (k, v) in hash {
+        match split_option(k, hash) {
+            Some((n, option)) if n == name && NUMBER_OPTIONS.contains(&option) => {
+                if let Some(value) = json_to_fluent(v.value()) {
+                    options.set(option, value);
+                }
+            }
+            _ => {}
+        }
+    }
+    n.options.merge(&options);
+}
@@ -0,0 +1,153 @@
+//! Handlebars helpers exposed as Fluent functions.
+//!
+//! Formatting that is already implemented as a Handlebars helper can be made available to
+//! translators by adding it to every bundle from the loader's customizer:
+//!
+//! ```rust
+//! use handlebars::*;
+//! use handlebars_fluent::*;
+//!
+//! fn upper(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+//!     let text = h.param(0).and_then(|p| p.value().as_str()).unwrap_or("");
+//!     out.write(&text.to_uppercase())?;
+//!     Ok(())
+//! }
+//!
+//! lazy_static::lazy_static! {
+//!     static ref HELPERS: Handlebars<'static> = {
+//!         let mut handlebars = Handlebars::new();
+//!         handlebars.register_helper("upper", Box::new(upper));
+//!         handlebars
+//!     };
+//! }
+//!
+//! simple_loader!(create_loader, "./tests/locales/", "en-US", core: "./tests/core.ftl",
+//!                customizer: |bundle, lang| {
+//!                    bridge::add_helper_functions(bundle, lang, &HELPERS, &[("UPPER", "upper")])
+//!                        .expect("Failed to add helper functions");
+//!                });
+//! ```
+//!
+//! With this, `{ UPPER($name) }` in an FTL file renders `{{{upper name}}}`. Named arguments
+//! are passed in the hash, so `{ TRUNCATE($title, length: 20) }` calls the `truncate` helper
+//! with `length=20`.
+//!
+//! Strings and numbers are passed as they are, lists as arrays and datetimes as RFC 3339
+//! strings. The helper is rendered against a context with the bundle's locale as its "lang"
+//! field. Its output is used verbatim, and errors make the function return a Fluent error.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentError, FluentValue};
+use handlebars::{Context, Handlebars, RenderContext, Renderable, StringOutput, Template};
+use serde_json::{json, Map, Number, Value as Json};
+use unic_langid::LanguageIdentifier;
+
+use crate::args::MAX_SAFE_INTEGER;
+use crate::types::{FluentDateTime, FluentList};
+
+/// The templates calling a helper, by the number of positional arguments and the names of the
+/// named ones
+type TemplateCache = Mutex<HashMap<(usize, Vec<String>), Arc<Template>>>;
+
+/// Add each `(function, helper)` pair of `helpers` to the bundle, so that `function` calls
+/// the helper registered as `helper` in `registry`
+pub fn add_helper_functions<R>(
+    bundle: &mut FluentBundle<R>,
+    lang: &LanguageIdentifier,
+    registry: &'static Handlebars<'static>,
+    helpers: &[(&str, &str)],
+) -> Result<(), FluentError> {
+    for &(function, helper) in helpers {
+        let helper = helper.to_string();
+        let lang = lang.to_string();
+        let templates = TemplateCache::default();
+        bundle.add_function(function, move |positional, named| {
+            call_helper(registry, &templates, &helper, &lang, positional, named)
+        })?;
+    }
+    Ok(())
+}
+
+/// Render `{{{helper params.[0] … key=hash.[key] …}}}` with the converted arguments
+fn call_helper<'a>(
+    registry: &Handlebars,
+    templates: &TemplateCache,
+    helper: &str,
+    lang: &str,
+    positional: &[FluentValue],
+    named: &FluentArgs,
+) -> FluentValue<'a> {
+    let keys: Vec<String> = named.iter().map(|(key, _)| key.to_string()).collect();
+    let Some(template) = helper_template(templates, helper, positional.len(), keys) else {
+        return FluentValue::Error;
+    };
+
+    let params: Vec<Json> = positional.iter().map(to_json).collect();
+    let hash: Map<String, Json> = named
+        .iter()
+        .map(|(key, value)| (key.to_string(), to_json(value)))
+        .collect();
+    let data = json!({"lang": lang, "params": params, "hash": hash});
+    let Ok(context) = Context::wraps(data) else {
+        return FluentValue::Error;
+    };
+    let mut out = StringOutput::new();
+    let rendered = template.render(registry, &context, &mut RenderContext::new(None), &mut out);
+    match rendered.map(|_| out.into_string()) {
+        Ok(Ok(output)) => output.into(),
+        _ => FluentValue::Error,
+    }
+}
+
+/// The compiled template calling `helper` with `arity` positional arguments and the named
+/// arguments `keys`, compiled on first use
+fn helper_template(
+    templates: &TemplateCache,
+    helper: &str,
+    arity: usize,
+    keys: Vec<String>,
+) -> Option<Arc<Template>> {
+    let mut templates = templates.lock().unwrap();
+    let key = (arity, keys);
+    if let Some(template) = templates.get(&key) {
+        return Some(template.clone());
+    }
+    let mut source = format!("{{{{{{{}", helper);
+    for i in 0..arity {
+        source.push_str(&format!(" params.[{}]", i));
+    }
+    for name in &key.1 {
+        source.push_str(&format!(" {}=hash.[{}]", name, name));
+    }
+    source.push_str("}}}");
+    let template = Arc::new(Template::compile(&source).ok()?);
+    templates.insert(key, template.clone());
+    Some(template)
+}
+
+/// Convert a value passed to a Fluent function to the value given to the helper
+fn to_json(value: &FluentValue) -> Json {
+    match value {
+        FluentValue::String(s) => Json::String(s.to_string()),
+        FluentValue::Number(n) => {
+            if n.value.fract() == 0.0 && n.value.abs() <= MAX_SAFE_INTEGER as f64 {
+                Json::from(n.value as i64)
+            } else {
+                Number::from_f64(n.value).map_or(Json::Null, Json::Number)
+            }
+        }
+        FluentValue::Custom(c) => {
+            if let Some(list) = c.as_any().downcast_ref::<FluentList>() {
+                Json::Array(list.items().iter().map(to_json).collect())
+            } else if let Some(datetime) = c.as_any().downcast_ref::<FluentDateTime>() {
+                Json::String(datetime.to_rfc3339())
+            } else {
+                Json::Null
+            }
+        }
+        FluentValue::None | FluentValue::Error => Json::Null,
+    }
+}
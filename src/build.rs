@@ -0,0 +1,137 @@
+//! Build script support for compiling locales into the binary.
+//!
+//! Add this crate to your `[build-dependencies]`, and generate the list of locales from
+//! `build.rs`:
+//!
+//! ```rust,no_run
+//! // in main()
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! handlebars_fluent::build::embed_locales("locales", format!("{}/locales.rs", out_dir))
+//!     .expect("Failed to embed the locales");
+//! ```
+//!
+//! Then pass it to [`simple_loader!()`](crate::simple_loader) with `embedded:`:
+//!
+//! ```rust,ignore
+//! simple_loader!(create_loader, embedded: include!(concat!(env!("OUT_DIR"), "/locales.rs")), "en-US");
+//! ```
+
+use std::fmt::Write as _;
+use std::fs::{self, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Write a list of [`EmbeddedLocale`](crate::loader::EmbeddedLocale)s to `out`, with the FTL
+/// files and `meta.toml` of every locale folder in `dir` included with `include_str!`.
+///
+/// Relative paths are resolved against the package being built. Cargo is told to rerun the
+/// build script when any of the files change.
+pub fn embed_locales(dir: impl AsRef<Path>, out: impl AsRef<Path>) -> io::Result<()> {
+    let dir = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) => Path::new(&manifest_dir).join(dir),
+        None => dir.as_ref().to_path_buf(),
+    };
+    println!("cargo:rerun-if-changed={}", dir.display());
+    fs::write(out, generate(&dir)?)
+}
+
+/// The entries of `dir` whose file type matches `is_dir`, sorted by name so that the output
+/// doesn't depend on the file system
+fn sorted_entries(dir: &Path, is_dir: bool) -> io::Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() == is_dir {
+            entries.push(entry.path());
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+fn path_str(path: &Path) -> io::Result<&str> {
+    path.to_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not valid UTF-8", path.display()),
+        )
+    })
+}
+
+/// The Rust expression for the locales in `dir`
+fn generate(dir: &Path) -> io::Result<String> {
+    let mut code = String::from("&[\n");
+    for locale_dir in sorted_entries(dir, true)? {
+        let Some(lang) = locale_dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        writeln!(code, "    ::handlebars_fluent::loader::EmbeddedLocale {{").unwrap();
+        writeln!(code, "        lang: {:?},", lang).unwrap();
+        writeln!(code, "        resources: &[").unwrap();
+        for file in sorted_entries(&locale_dir, false)? {
+            // Like the runtime loader, only FTL files are loaded
+            if file.extension().and_then(|e| e.to_str()) != Some("ftl") {
+                continue;
+            }
+            println!("cargo:rerun-if-changed={}", file.display());
+            writeln!(code, "            include_str!({:?}),", path_str(&file)?).unwrap();
+        }
+        writeln!(code, "        ],").unwrap();
+        let metadata = locale_dir.join("meta.toml");
+        if metadata.is_file() {
+            println!("cargo:rerun-if-changed={}", metadata.display());
+            writeln!(
+                code,
+                "        metadata: Some(include_str!({:?})),",
+                path_str(&metadata)?
+            )
+            .unwrap();
+        } else {
+            writeln!(code, "        metadata: None,").unwrap();
+        }
+        writeln!(code, "    }},").unwrap();
+    }
+    code.push(']');
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_generate() -> Result<(), Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        let fr = dir.path().join("fr");
+        fs::create_dir(&fr)?;
+        fs::write(fr.join("main.ftl"), "foo = bar\n")?;
+        fs::write(fr.join("main.ftl.swp"), "")?;
+        fs::write(fr.join("meta.toml"), "native-name = \"Français\"\n")?;
+        fs::create_dir(dir.path().join("de"))?;
+        fs::write(dir.path().join("core.ftl"), "foo = bar\n")?;
+
+        let code = generate(dir.path())?;
+        let expected = format!(
+            r#"&[
+    ::handlebars_fluent::loader::EmbeddedLocale {{
+        lang: "de",
+        resources: &[
+        ],
+        metadata: None,
+    }},
+    ::handlebars_fluent::loader::EmbeddedLocale {{
+        lang: "fr",
+        resources: &[
+            include_str!({:?}),
+        ],
+        metadata: Some(include_str!({:?})),
+    }},
+]"#,
+            fr.join("main.ftl").to_str().unwrap(),
+            fr.join("meta.toml").to_str().unwrap()
+        );
+        assert_eq!(code, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Locale-aware Fluent functions, enabled with the `icu` feature.
+//!
+//! These are registered on every bundle created by [`create_bundle()`](crate::loader::create_bundle),
+//! before the customizer runs:
+//!
+//! - `NUMBER($n, style: "currency", currency: "EUR", minimumFractionDigits: 2)`, supporting
+//!   the `style` (`decimal`, `percent`, `currency`), `currency`, `useGrouping`,
+//!   `minimumIntegerDigits`, `minimumFractionDigits` and `maximumFractionDigits` options.
+//!   Numbers are formatted with the locale's digits and separators even outside of `NUMBER()`.
+//! - `DATETIME($date, dateStyle: "long", timeStyle: "short")`, for RFC 3339 strings and
+//!   Unix timestamps (in seconds). The styles are `full`, `long`, `medium` and `short`.
+//! - `LIST($a, $b, $c, type: "disjunction", style: "short")`, joining its arguments into a
+//!   conjunction (the default), disjunction or unit list. Lists passed as arguments are
+//!   flattened, so `LIST($authors, type: "disjunction")` restyles an array argument.
+//! - `PLATFORM()`, returning `linux`, `macos`, `windows`, `android` or `other` for the
+//!   operating system the program runs on, like in Fluent.js, for selectors such as
+//!   `{ PLATFORM() -> [macos] ⌘ *[other] Ctrl }`.
+//!
+//! All formatting data is compiled into the binary, no network or filesystem access is needed.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentError, FluentValue};
+
+use crate::types::{FluentDateTime, FluentList};
+
+/// Add the `NUMBER`, `DATETIME`, `LIST` and `PLATFORM` functions to a bundle, and format numbers
+/// according to the bundle's locale.
+pub fn add_builtin_functions<R>(bundle: &mut FluentBundle<R>) -> Result<(), FluentError> {
+    bundle.add_function("NUMBER", fluent_bundle::builtins::NUMBER)?;
+    bundle.add_function("DATETIME", DATETIME)?;
+    bundle.add_function("LIST", LIST)?;
+    bundle.add_function("PLATFORM", PLATFORM)?;
+    bundle.set_formatter(Some(crate::intl::format_value));
+    Ok(())
+}
+
+/// Convert a value passed to a Fluent function to a datetime
+fn to_datetime(value: &FluentValue) -> Option<FluentDateTime> {
+    match value {
+        FluentValue::String(s) => FluentDateTime::parse_rfc3339(s),
+        FluentValue::Number(n) => Some(FluentDateTime::new(n.value as i64, 0)),
+        FluentValue::Custom(c) => c.as_any().downcast_ref::<FluentDateTime>().cloned(),
+        _ => None,
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn DATETIME<'a>(positional: &[FluentValue<'a>], named: &FluentArgs) -> FluentValue<'a> {
+    let Some(mut datetime) = positional.first().and_then(to_datetime) else {
+        return FluentValue::Error;
+    };
+    datetime.options.merge(named);
+    FluentValue::Custom(Box::new(datetime))
+}
+
+#[allow(non_snake_case)]
+pub fn LIST<'a>(positional: &[FluentValue<'a>], named: &FluentArgs) -> FluentValue<'a> {
+    let mut items = Vec::new();
+    for value in positional {
+        match value {
+            FluentValue::Custom(c) => match c.as_any().downcast_ref::<FluentList>() {
+                Some(list) => items.extend(list.items().iter().cloned()),
+                None => items.push(value.into_owned()),
+            },
+            _ => items.push(value.into_owned()),
+        }
+    }
+    let mut list = FluentList::new(items);
+    list.options.merge(named);
+    FluentValue::Custom(Box::new(list))
+}
+
+#[allow(non_snake_case)]
+pub fn PLATFORM<'a>(_positional: &[FluentValue<'a>], _named: &FluentArgs) -> FluentValue<'a> {
+    match std::env::consts::OS {
+        os @ ("linux" | "macos" | "windows" | "android") => os.into(),
+        _ => "other".into(),
+    }
+}
@@ -1,22 +1,200 @@
 use handlebars::{
-    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderErrorReason,
-    Renderable,
+    BlockContext, BlockParams, Context, Decorator, DecoratorDef, Handlebars, Helper, HelperDef,
+    HelperResult, Output, RenderContext, RenderError, RenderErrorReason, Renderable, ScopedJson,
 };
 
 use fluent_bundle::{FluentArgs, FluentValue};
 use handlebars::template::{Parameter, TemplateElement};
-use serde_json::Value as Json;
+use handlebars::Template;
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
 use std::io;
+use std::sync::{Arc, Mutex};
+use unic_langid::LanguageIdentifier;
 
-use crate::Loader;
+use crate::args;
+use crate::state;
+use crate::types::parse_offset;
+use crate::{Loader, MarkupPolicy};
 
 pub struct FluentHelper<L> {
     loader: L,
+    param_name: &'static str,
+    dedent: bool,
+    coerce_numbers: bool,
+    default_args: Vec<(String, DefaultArg)>,
+    scope_by_template: bool,
+    source_keyed: bool,
+    templates: Option<TemplateCache>,
+    markup: Option<MarkupPolicy>,
+}
+
+/// Messages compiled as templates, by locale and id, along with the text they were compiled
+/// from
+type TemplateCache = Mutex<HashMap<(String, String), (String, Arc<Template>)>>;
+
+/// Where the value of a default argument comes from
+enum DefaultArg {
+    Value(Json),
+    Path(Vec<String>),
 }
 
 impl<L> FluentHelper<L> {
     pub fn new(loader: L) -> Self {
-        Self { loader }
+        Self {
+            loader,
+            param_name: "fluentparam",
+            dedent: false,
+            coerce_numbers: false,
+            default_args: Vec::new(),
+            scope_by_template: false,
+            source_keyed: false,
+            templates: None,
+            markup: None,
+        }
+    }
+
+    /// Apply `policy` to the markup in formatted messages, and HTML-escape string arguments
+    /// before formatting, so that only translators can add markup.
+    ///
+    /// Without a policy, messages and arguments are both output verbatim. The markup of
+    /// defaults given with `default=…` is kept as it is.
+    pub fn with_markup_policy(mut self, policy: MarkupPolicy) -> Self {
+        self.markup = Some(policy);
+        self
+    }
+
+    /// Render formatted messages as Handlebars templates against the current context, so that
+    /// translations can use context values and helpers. Since braces are Fluent syntax, they
+    /// are written as string literals in FTL files:
+    ///
+    /// ```fluent
+    /// signed-in = Signed in as {"{{"}user.name{"}}"}
+    /// ```
+    ///
+    /// This lets translators run any helper that is registered, so only enable it for
+    /// translations you trust. Expressions in messages are escaped like in any other template,
+    /// unless written with triple braces. Braces and backslashes in string arguments are
+    /// replaced with placeholders until the template is rendered, so argument values are never
+    /// rendered as templates.
+    ///
+    /// The last compiled template of each message is reused as long as its formatted text is
+    /// the same, so messages whose arguments change between renders are compiled every time.
+    pub fn render_as_template(mut self, render: bool) -> Self {
+        self.templates = if render {
+            Some(Mutex::new(HashMap::new()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Take the source text of a message as the first parameter instead of its id, gettext
+    /// style: `{{t "Sign in" id="nav-sign-in"}}`.
+    ///
+    /// The text is rendered when no locale has the message. Without an `id`, the message id is
+    /// derived from the text with [`source_id()`].
+    pub fn source_keyed(mut self, source_keyed: bool) -> Self {
+        self.source_keyed = source_keyed;
+        self
+    }
+
+    /// Outside of `{{#fluent-scope}}` blocks, resolve relative ids like `.title` against the
+    /// name of the template being rendered, without any folders or extensions. In a template
+    /// named `pages/checkout.hbs`, `.title` is `checkout-title`.
+    pub fn scope_by_template(mut self, scope: bool) -> Self {
+        self.scope_by_template = scope;
+        self
+    }
+
+    /// Turn a relative id like `.title` into `<scope>-title`
+    fn resolve_id<'reg: 'rc, 'rc>(
+        &self,
+        id: &str,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<String, RenderError> {
+        let Some(relative) = id.strip_prefix('.') else {
+            return Ok(id.to_string());
+        };
+        let scope = match state::get(state::SCOPE, h, reg, context, rcx)? {
+            Some(scope) => Some(scope),
+            None if self.scope_by_template => rcx.get_current_template_name().map(|name| {
+                let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+                name.split('.').next().unwrap_or(name).to_string()
+            }),
+            None => None,
+        };
+        match scope {
+            Some(scope) => Ok(format!("{}-{}", scope, relative)),
+            None => Err(RenderErrorReason::Other(format!(
+                "Relative message id {} used outside of a {{{{#fluent-scope}}}} block",
+                id
+            ))
+            .into()),
+        }
+    }
+
+    /// Pass `name` to every message, unless the helper is given a `name` argument itself.
+    ///
+    /// Values are converted like hash arguments, so arrays become lists.
+    pub fn with_default_arg(mut self, name: impl Into<String>, value: impl Into<Json>) -> Self {
+        self.default_args
+            .push((name.into(), DefaultArg::Value(value.into())));
+        self
+    }
+
+    /// Pass `name` to every message with the value at a dotted `path` in the context, such as
+    /// `user.name`, unless the helper is given a `name` argument itself.
+    ///
+    /// If the path isn't there, an earlier default for `name` is used, if any.
+    pub fn with_context_arg(mut self, name: impl Into<String>, path: &str) -> Self {
+        let path = path.split('.').map(String::from).collect();
+        self.default_args
+            .push((name.into(), DefaultArg::Path(path)));
+        self
+    }
+
+    fn default_args(&self, context: &Context) -> FluentArgs<'static> {
+        let mut args = FluentArgs::new();
+        for (name, arg) in &self.default_args {
+            let value = match arg {
+                DefaultArg::Value(value) => Some(value),
+                DefaultArg::Path(path) => path
+                    .iter()
+                    .try_fold(context.data(), |value, key| value.get(key)),
+            };
+            if let Some(value) = value.and_then(|v| args::value_to_fluent(v, self.coerce_numbers)) {
+                args.set(name.clone(), value);
+            }
+        }
+        args
+    }
+
+    /// Pass string arguments and parameter bodies that look like numbers as numbers, so that
+    /// they select plural variants.
+    ///
+    /// Without this, strings are only converted when marked with `type="number"` on a
+    /// parameter block, or `<name>-type="number"` in the hash.
+    pub fn coerce_numbers(mut self, coerce: bool) -> Self {
+        self.coerce_numbers = coerce;
+        self
+    }
+
+    /// Dedent and trim the bodies of parameter blocks by default.
+    ///
+    /// Individual blocks can override this with `dedent=true` or `dedent=false`.
+    pub fn dedent_params(mut self, dedent: bool) -> Self {
+        self.dedent = dedent;
+        self
+    }
+
+    /// Recognise parameter blocks named `name` instead of `fluentparam`
+    pub fn with_param_name(mut self, name: &'static str) -> Self {
+        self.param_name = name;
+        self
     }
 }
 
@@ -67,72 +245,916 @@ impl<L: Loader + Send + Sync> HelperDef for FluentHelper<L> {
             .into());
         };
 
-        let mut args = if h.hash().is_empty() {
-            None
+        let string_option = |name: &str| match h.hash_get(name).map(|v| v.value()) {
+            Some(Json::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(RenderError::from(
+                RenderErrorReason::ParamTypeMismatchForName("fluent", name.into(), "string".into()),
+            )),
+            None => Ok(None),
+        };
+        let (id, fallback) = if self.source_keyed {
+            let source = id;
+            let id = string_option("id")?.unwrap_or_else(|| source_id(source));
+            (id, Fallback::Text(source.clone()))
         } else {
-            let mut fluent_args = FluentArgs::new();
-            for (k, v) in h.hash() {
-                let k = k.to_owned();
-                match v.value() {
-                    // `Number::as_f64` can't fail here because we haven't
-                    // enabled `arbitrary_precision` feature in `serde_json`.
-                    Json::Number(n) => fluent_args.set(k, n.as_f64().unwrap()),
-                    Json::String(s) => fluent_args.set(k, s.to_owned()),
-                    _ => continue,
-                }
+            match string_option("default")? {
+                Some(default) => (id.clone(), Fallback::Text(default)),
+                None => (id.clone(), Fallback::Loader),
             }
-            Some(fluent_args)
         };
 
-        if let Some(tpl) = h.template() {
-            if args.is_none() {
-                args = Some(FluentArgs::new());
+        let response = self
+            .format(&id, fallback, h, reg, context, rcx)?
+            .unwrap_or_default();
+        out.write(&response)
+            .map_err(|e| RenderErrorReason::NestedError(Box::new(e)).into())
+    }
+}
+
+/// What to render when no locale has a message
+enum Fallback {
+    /// The loader's placeholder for unknown messages
+    Loader,
+    /// A default text
+    Text(String),
+    /// Nothing, or the default text if one is given, leaving it to the caller. Messages that
+    /// can't be formatted without errors are treated as missing.
+    Missing(Option<String>),
+}
+
+impl<L: Loader + Send + Sync> FluentHelper<L> {
+    /// Format the message `id` with the arguments given to the helper, returning `None` if it's
+    /// missing and `fallback` is [`Fallback::Missing`] without a default
+    fn format<'reg: 'rc, 'rc>(
+        &self,
+        id: &str,
+        fallback: Fallback,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<Option<String>, RenderError> {
+        // Other values of `format` are passed on as a variable
+        let markdown = matches!(
+            h.hash_get("format").map(|v| v.value()),
+            Some(Json::String(format)) if format == "markdown"
+        );
+        let reserved: &[&str] = match (self.source_keyed, markdown) {
+            (true, true) => &["lang", "id", "format"],
+            (true, false) => &["lang", "id"],
+            (false, true) => &["lang", "default", "format"],
+            (false, false) => &["lang", "default"],
+        };
+
+        let time_zone = match context.data().get("timezone") {
+            Some(Json::String(zone)) => Some(parse_offset(zone).ok_or_else(|| {
+                RenderErrorReason::Other(format!("Invalid time zone offset {}", zone))
+            })?),
+            _ => None,
+        };
+
+        // Defaults come first so that explicit arguments replace them
+        let mut args = self.default_args(context);
+        if !h.hash().is_empty() {
+            for (name, value) in
+                args::from_hash(h.hash(), reserved, time_zone, self.coerce_numbers)?
+            {
+                args.set(name, value);
             }
-            let args = args.as_mut().unwrap();
+        }
+
+        if let Some(tpl) = h.template() {
             for element in &tpl.elements {
-                if let TemplateElement::HelperBlock(ref block) = element {
-                    if block.name != Parameter::Name("fluentparam".into()) {
+                let block = match element {
+                    TemplateElement::HelperBlock(ref block)
+                        if block.name == Parameter::Name(self.param_name.into()) =>
+                    {
+                        block
+                    }
+                    // Whitespace and comments are fine, anything else would be silently lost
+                    TemplateElement::RawString(ref s) if s.trim().is_empty() => continue,
+                    TemplateElement::Comment(_) => continue,
+                    TemplateElement::RawString(ref s) => {
+                        return Err(RenderErrorReason::Other(format!(
+                            "{{{{{}}}}} can only contain {{{{{}}}}} elements, not text {:?}",
+                            h.name(),
+                            self.param_name,
+                            s.trim()
+                        ))
+                        .into());
+                    }
+                    TemplateElement::HelperBlock(ref block)
+                    | TemplateElement::Expression(ref block)
+                    | TemplateElement::HtmlExpression(ref block) => {
                         return Err(RenderErrorReason::Other(format!(
-                            "{{{{fluent}}}} can only contain {{{{fluentparam}}}} elements, not {}",
-                            block.name.expand_as_name(reg, context, rcx).unwrap()
+                            "{{{{{}}}}} can only contain {{{{#{}}}}} blocks, not {}",
+                            h.name(),
+                            self.param_name,
+                            block.name.expand_as_name(reg, context, rcx)?
                         ))
                         .into());
                     }
-                    let id = if let Some(el) = block.params.first() {
-                        if let Parameter::Literal(Json::String(ref s)) = *el {
-                            s
-                        } else {
+                    _ => {
+                        return Err(RenderErrorReason::Other(format!(
+                            "{{{{{}}}}} can only contain {{{{#{}}}}} blocks",
+                            h.name(),
+                            self.param_name,
+                        ))
+                        .into());
+                    }
+                };
+                let id = match block.params.first() {
+                    Some(param) => param.expand(reg, context, rcx)?,
+                    None => {
+                        return Err(
+                            RenderErrorReason::ParamNotFoundForIndex(self.param_name, 0).into()
+                        )
+                    }
+                };
+                let id = if let Json::String(ref s) = *id.value() {
+                    s.clone()
+                } else {
+                    return Err(RenderErrorReason::ParamTypeMismatchForName(
+                        self.param_name,
+                        "0".into(),
+                        "string".into(),
+                    )
+                    .into());
+                };
+                let dedent = match block.hash.get("dedent") {
+                    Some(param) => param.expand(reg, context, rcx)?.value().as_bool() == Some(true),
+                    None => self.dedent,
+                };
+                let number = match block.hash.get("type") {
+                    Some(param) => match param.expand(reg, context, rcx)?.value().as_str() {
+                        Some("number") => true,
+                        Some("string") => false,
+                        _ => {
                             return Err(RenderErrorReason::ParamTypeMismatchForName(
-                                "fluentparam",
-                                "0".into(),
-                                "string".into(),
+                                self.param_name,
+                                "type".into(),
+                                "\"number\" or \"string\"".into(),
                             )
-                            .into());
+                            .into())
                         }
+                    },
+                    None => false,
+                };
+                if let Some(ref tpl) = block.template {
+                    let mut s = StringOutput::default();
+                    tpl.render(reg, context, rcx, &mut s)?;
+                    let text = if dedent { dedent_text(&s.s) } else { s.s };
+                    let value = if number {
+                        args::parse_number(&text).ok_or_else(|| {
+                            RenderErrorReason::ParamTypeMismatchForName(
+                                self.param_name,
+                                id.clone(),
+                                "number".into(),
+                            )
+                        })?
+                    } else if self.coerce_numbers && !block.hash.contains_key("type") {
+                        args::parse_number(&text).unwrap_or_else(|| text.into())
                     } else {
-                        return Err(
-                            RenderErrorReason::ParamNotFoundForIndex("fluentparam", 0).into()
-                        );
+                        FluentValue::String(text.into())
                     };
-                    if let Some(ref tpl) = block.template {
-                        let mut s = StringOutput::default();
-                        tpl.render(reg, context, rcx, &mut s)?;
-                        args.set(id, FluentValue::String(s.s.into()));
-                    }
+                    args.set(id, value);
                 }
             }
         }
-        let lang = context
-            .data()
-            .get("lang")
-            .expect("Language not set in context")
-            .as_str()
-            .expect("Language must be string")
-            .parse()
-            .expect("Language not valid identifier");
+        let lang = self.loader.negotiate(&resolve_lang(h, reg, context, rcx)?);
 
-        let response = self.loader.lookup(&lang, id, args.as_ref());
-        out.write(&response)
+        let args = if args.iter().next().is_none() {
+            None
+        } else {
+            Some(args)
+        };
+        let args = match self.templates {
+            Some(_) => args.map(args::escape_mustaches),
+            None => args,
+        };
+        let args = match self.markup {
+            Some(_) => args.map(args::escape_html),
+            None => args,
+        };
+        let id = self.resolve_id(id, h, reg, context, rcx)?;
+        let args = args.as_ref();
+        // Defaults come from the template rather than translators, so they're kept as they are.
+        // Markdown is sanitized once rendered instead.
+        let markup = |text: String| match self.markup {
+            Some(ref policy) if !markdown => policy.apply(&text),
+            _ => text,
+        };
+        let default = |default: String| self.loader.format_text(&lang, &default, args);
+        let text = match (id.strip_prefix('-'), fallback) {
+            (Some(term_id), Fallback::Text(text)) => Some(
+                self.loader
+                    .try_lookup_term(&lang, term_id, args)
+                    .map(markup)
+                    .unwrap_or_else(|| default(text)),
+            ),
+            (Some(term_id), _) => Some(markup(self.loader.lookup_term(&lang, term_id, args))),
+            (None, Fallback::Loader) => Some(markup(self.loader.lookup(&lang, &id, args))),
+            (None, Fallback::Text(text)) => Some(
+                self.loader
+                    .try_lookup(&lang, &id, args)
+                    .map(markup)
+                    .unwrap_or_else(|| default(text)),
+            ),
+            (None, Fallback::Missing(text)) => self
+                .loader
+                .try_lookup_complete(&lang, &id, args)
+                .map(markup)
+                .or_else(|| text.map(default)),
+        };
+        let text = match (text, &self.templates) {
+            (Some(text), Some(templates)) => {
+                let template = compile_cached(templates, &lang, &id, text)?;
+                let mut out = StringOutput::default();
+                let mut rcx = rcx.clone();
+                template.render(reg, context, &mut rcx, &mut out)?;
+                Some(args::restore_mustaches(&out.s))
+            }
+            (text, _) => text,
+        };
+        match text {
+            Some(text) if markdown => render_markdown(&text).map(Some),
+            text => Ok(text),
+        }
+    }
+}
+
+#[cfg(feature = "markdown")]
+fn render_markdown(text: &str) -> Result<String, RenderError> {
+    Ok(crate::markdown::render(text))
+}
+
+#[cfg(not(feature = "markdown"))]
+fn render_markdown(_: &str) -> Result<String, RenderError> {
+    Err(RenderErrorReason::Other("format=\"markdown\" requires the markdown feature".into()).into())
+}
+
+/// Compile `text` as the template for message `id` in `lang`, reusing the last compiled
+/// template if the text hasn't changed
+fn compile_cached(
+    templates: &TemplateCache,
+    lang: &LanguageIdentifier,
+    id: &str,
+    text: String,
+) -> Result<Arc<Template>, RenderError> {
+    let key = (lang.to_string(), id.to_string());
+    if let Some((source, template)) = templates.lock().unwrap().get(&key) {
+        if *source == text {
+            return Ok(template.clone());
+        }
+    }
+    let template = Arc::new(Template::compile(&text)?);
+    templates
+        .lock()
+        .unwrap()
+        .insert(key, (text, template.clone()));
+    Ok(template)
+}
+
+/// `helperMissing` hook rendering unknown mustaches as Fluent messages, so that
+/// `{{welcome-banner}}` or `{{welcome-banner name=user.name}}` work like
+/// `{{fluent "welcome-banner" …}}` when there's no helper or context field of that name:
+///
+/// ```rust
+/// # use handlebars_fluent::*;
+/// # use handlebars::*;
+/// # simple_loader!(create_loader, "./tests/locales/", "en-US");
+/// # fn init(handlebars: &mut Handlebars) {
+/// let fluent = FluentHelper::new(create_loader());
+/// handlebars.register_helper("helperMissing", Box::new(FluentMissingHelper::new(fluent)));
+/// # }
+/// ```
+///
+/// Only names that are valid message ids are looked up, and `default=…` is rendered when
+/// there's no such message. When there's no default either, or the locale isn't set or the
+/// message can't be formatted without errors, the fallback hook is called if one was given with [`with_fallback()`](Self::with_fallback),
+/// otherwise Handlebars' usual behaviour is kept: a missing value renders nothing and a
+/// missing helper is an error.
+///
+/// In strict mode, Handlebars reports a missing value without calling `helperMissing`, so
+/// only mustaches with parameters or hash entries, like `{{welcome-banner name=user.name}}`,
+/// are looked up.
+pub struct FluentMissingHelper<L> {
+    fluent: FluentHelper<L>,
+    fallback: Option<Box<dyn HelperDef + Send + Sync>>,
+}
+
+impl<L> FluentMissingHelper<L> {
+    pub fn new(fluent: FluentHelper<L>) -> Self {
+        Self {
+            fluent,
+            fallback: None,
+        }
+    }
+
+    /// Call `fallback`, such as a previously registered `helperMissing`, for names that aren't
+    /// messages
+    pub fn with_fallback(mut self, fallback: impl HelperDef + Send + Sync + 'static) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+}
+
+/// Whether `name` is a valid Fluent message identifier, `[a-zA-Z][a-zA-Z0-9_-]*`
+fn is_message_id(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+impl<L: Loader + Send + Sync> HelperDef for FluentMissingHelper<L> {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        if is_message_id(h.name()) {
+            let default = match h.hash_get("default").map(|d| d.value()) {
+                Some(Json::String(default)) => Some(default.clone()),
+                _ => None,
+            };
+            // Without a locale, or with errors, the name is left to the usual behaviour
+            let fallback = Fallback::Missing(default);
+            if let Ok(Some(message)) = self.fluent.format(h.name(), fallback, h, reg, context, rcx)
+            {
+                return out
+                    .write(&message)
+                    .map_err(|e| RenderErrorReason::NestedError(Box::new(e)).into());
+            }
+        }
+        match self.fallback {
+            Some(ref fallback) => fallback.call(h, reg, context, rcx, out),
+            None if h.params().is_empty() && h.hash().is_empty() => Ok(()),
+            None => Err(RenderErrorReason::HelperNotFound(h.name().to_string()).into()),
+        }
+    }
+}
+
+/// The message id used for a source text without an explicit id: `t-` followed by the 64-bit
+/// FNV-1a hash of its UTF-8 bytes in hexadecimal, e.g. `t-b64127e4e4f0b575` for `Log out`.
+///
+/// Extraction tooling can use this to name the messages of `{{t}}` calls without an `id`.
+pub fn source_id(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("t-{:016x}", hash)
+}
+
+/// Strips the indentation common to all non-blank lines, and surrounding whitespace
+fn dedent_text(text: &str) -> String {
+    let indent = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Registered under the parameter name so that parameter blocks used outside of a
+/// [`FluentHelper`] block produce an error instead of rendering nothing
+pub struct FluentParamHelper {
+    fluent_name: &'static str,
+}
+
+impl FluentParamHelper {
+    /// `fluent_name` is the name the [`FluentHelper`] is registered under, for error messages
+    pub fn new(fluent_name: &'static str) -> Self {
+        Self { fluent_name }
+    }
+}
+
+impl Default for FluentParamHelper {
+    fn default() -> Self {
+        Self::new("fluent")
+    }
+}
+
+impl HelperDef for FluentParamHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        _: &mut dyn Output,
+    ) -> HelperResult {
+        Err(RenderErrorReason::Other(format!(
+            "{{{{#{}}}}} can only be used directly inside a {{{{#{}}}}} block",
+            h.name(),
+            self.fluent_name
+        ))
+        .into())
+    }
+}
+
+/// The "lang" field of the context, erroring if it's there but isn't a string
+fn context_lang(data: &Json) -> Result<Option<LanguageIdentifier>, RenderError> {
+    match data.get("lang") {
+        Some(Json::String(lang)) => parse_lang(lang).map(Some),
+        Some(_) => Err(RenderErrorReason::Other("\"lang\" must be a string".into()).into()),
+        None => Ok(None),
+    }
+}
+
+fn parse_lang(lang: &str) -> Result<LanguageIdentifier, RenderError> {
+    lang.parse()
+        .map_err(|_| RenderErrorReason::Other(format!("Invalid language {:?}", lang)).into())
+}
+
+/// The locale to render in. This is the first of:
+///
+/// - the `lang=…` hash entry
+/// - the locale of an enclosing `{{#fluent-locale}}`
+/// - the locale set for the whole render with `{{*fluent-lang}}`
+/// - the "lang" field of the context, if a decorator has replaced it
+/// - the "lang" field of the root context
+/// - the "lang" field of the current block, e.g. passed to a partial with `{{> p lang=lang}}`
+fn resolve_lang<'reg: 'rc, 'rc>(
+    h: &Helper<'rc>,
+    reg: &'reg Handlebars,
+    context: &'rc Context,
+    rcx: &mut RenderContext<'reg, 'rc>,
+) -> Result<LanguageIdentifier, RenderError> {
+    if let Some(lang) = h.hash_get("lang") {
+        return match lang.value().as_str() {
+            Some(lang) => parse_lang(lang),
+            None => Err(RenderErrorReason::ParamTypeMismatchForName(
+                "fluent",
+                "lang".into(),
+                "string".into(),
+            )
+            .into()),
+        };
+    }
+    for name in [state::LOCALE, state::LANG] {
+        if let Some(lang) = state::get(name, h, reg, context, rcx)? {
+            return parse_lang(&lang);
+        }
+    }
+    if let Some(lang) = current_lang(context, rcx)? {
+        return Ok(lang);
+    }
+    if let Some(lang) = rcx.block().and_then(|b| b.base_value()) {
+        if let Some(lang) = context_lang(lang)? {
+            return Ok(lang);
+        }
+    }
+    Err(RenderErrorReason::Other(
+        "No language set: add a \"lang\" field to the context or use {{*fluent-lang}}".into(),
+    )
+    .into())
+}
+
+/// The "lang" field of the context given to the render, or of the one that replaced it
+fn current_lang(
+    context: &Context,
+    rcx: &RenderContext,
+) -> Result<Option<LanguageIdentifier>, RenderError> {
+    if let Some(modified) = rcx.context() {
+        if let Some(lang) = context_lang(modified.data())? {
+            return Ok(Some(lang));
+        }
+    }
+    context_lang(context.data())
+}
+
+/// Decorator setting the locale for the rest of the render, including partials:
+///
+/// ```hbs
+/// {{*fluent-lang page.language}}
+/// ```
+///
+/// Without a parameter, the "lang" field of the context is used. This takes precedence over
+/// the "lang" field of the context in all helpers from this crate, so that they keep working
+/// in partials or helpers rendering with another context.
+pub struct FluentLangDecorator;
+
+impl DecoratorDef for FluentLangDecorator {
+    fn call<'reg: 'rc, 'rc>(
+        &'reg self,
+        d: &Decorator<'rc>,
+        _: &'reg Handlebars<'reg>,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<(), RenderError> {
+        let lang = match d.param(0).map(|p| p.value()) {
+            Some(Json::String(lang)) => parse_lang(lang)?,
+            Some(_) => {
+                return Err(RenderErrorReason::ParamTypeMismatchForName(
+                    "fluent-lang",
+                    "0".into(),
+                    "string".into(),
+                )
+                .into())
+            }
+            None => current_lang(context, rcx)?.ok_or_else(|| {
+                RenderErrorReason::Other("{{*fluent-lang}} needs a \"lang\" field".into())
+            })?,
+        };
+        state::set(state::LANG, Some(lang.to_string()), rcx);
+        Ok(())
+    }
+}
+
+/// Outputs the locale the loader picks for the context's "lang", e.g. for `<html lang="…">`
+pub struct FluentLangHelper<L> {
+    loader: L,
+}
+
+impl<L> FluentLangHelper<L> {
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+}
+
+impl<L: Loader + Send + Sync> HelperDef for FluentLangHelper<L> {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let lang = self.loader.negotiate(&resolve_lang(h, reg, context, rcx)?);
+        out.write(&lang.to_string())
             .map_err(|e| RenderErrorReason::NestedError(Box::new(e)).into())
     }
 }
+
+/// Outputs `ltr` or `rtl` for the locale the loader picks, e.g. for `<html dir="…">`
+pub struct FluentDirHelper<L> {
+    loader: L,
+}
+
+impl<L> FluentDirHelper<L> {
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+}
+
+impl<L: Loader + Send + Sync> HelperDef for FluentDirHelper<L> {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let lang = self.loader.negotiate(&resolve_lang(h, reg, context, rcx)?);
+        out.write(self.loader.direction(&lang).as_str())
+            .map_err(|e| RenderErrorReason::NestedError(Box::new(e)).into())
+    }
+}
+
+/// Block helper rendering its contents once per locale of the loader, e.g. for a language
+/// switcher:
+///
+/// ```hbs
+/// {{#each-fluent-locale}}
+///     <a href="/{{code}}" lang="{{code}}" dir="{{dir}}">{{native_name}} ({{display_name}})</a>
+/// {{/each-fluent-locale}}
+/// ```
+///
+/// Each locale has a `code`, its `native_name`, its `display_name` in the current locale,
+/// its `dir` and an `is_current` flag. Names fall back to the native name and then to the code.
+/// `@index`, `@first`, `@last` and a block parameter (`as |locale|`) work like in `{{#each}}`.
+pub struct FluentLocalesHelper<L> {
+    loader: L,
+}
+
+impl<L> FluentLocalesHelper<L> {
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+}
+
+impl<L: Loader + Send + Sync> HelperDef for FluentLocalesHelper<L> {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let current = self.loader.negotiate(&resolve_lang(h, reg, context, rcx)?);
+        let locales = self.loader.locales();
+        let template = match h.template() {
+            Some(t) if !locales.is_empty() => t,
+            _ => {
+                if let Some(inverse) = h.inverse() {
+                    inverse.render(reg, context, rcx, out)?;
+                }
+                return Ok(());
+            }
+        };
+
+        rcx.push_block(BlockContext::new());
+        let len = locales.len();
+        for (i, lang) in locales.iter().enumerate() {
+            let code = lang.to_string();
+            let native_name = self.loader.native_name(lang);
+            let display_name = self
+                .loader
+                .display_name(&current, lang)
+                .or_else(|| native_name.clone())
+                .unwrap_or_else(|| code.clone());
+            let value = json!({
+                "native_name": native_name.unwrap_or_else(|| code.clone()),
+                "code": code,
+                "display_name": display_name,
+                "dir": self.loader.direction(lang).as_str(),
+                "is_current": *lang == current,
+            });
+            if let Some(block) = rcx.block_mut() {
+                block.set_local_var("index", json!(i));
+                block.set_local_var("first", json!(i == 0));
+                block.set_local_var("last", json!(i == len - 1));
+                if let Some(name) = h.block_param() {
+                    let mut params = BlockParams::new();
+                    params.add_value(name, value.clone())?;
+                    block.set_block_params(params);
+                }
+                block.set_base_value(value);
+            }
+            template.render(reg, context, rcx, out)?;
+        }
+        rcx.pop_block();
+        Ok(())
+    }
+}
+
+/// Block helper rendering its contents in another locale:
+///
+/// ```hbs
+/// {{#each reviews}}
+///     {{#fluent-locale language}}{{fluent "review-by" author=author}}{{/fluent-locale}}
+/// {{/each}}
+/// ```
+///
+/// This affects every helper from this crate used inside the block, unless it's given a locale
+/// with `lang=…` itself.
+pub struct FluentLocaleHelper;
+
+impl HelperDef for FluentLocaleHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let lang = match h.param(0).map(|p| p.value()) {
+            Some(Json::String(lang)) => parse_lang(lang)?,
+            Some(_) => {
+                return Err(RenderErrorReason::ParamTypeMismatchForName(
+                    "fluent-locale",
+                    "0".into(),
+                    "string".into(),
+                )
+                .into())
+            }
+            None => return Err(RenderErrorReason::ParamNotFoundForIndex("fluent-locale", 0).into()),
+        };
+        let Some(template) = h.template() else {
+            return Ok(());
+        };
+        let previous = state::get(state::LOCALE, h, reg, context, rcx)?;
+        state::set(state::LOCALE, Some(lang.to_string()), rcx);
+        let result = template.render(reg, context, rcx, out);
+        state::set(state::LOCALE, previous, rcx);
+        result
+    }
+}
+
+/// Outputs the CLDR plural category (`zero`, `one`, `two`, `few`, `many` or `other`) of a
+/// number in the current locale, for templates whose structure depends on it:
+///
+/// ```hbs
+/// {{#if (eq (fluent-plural count) "one")}}…{{/if}}
+/// {{fluent-plural position type="ordinal"}}
+/// ```
+///
+/// The number may also be given as a string. The category is the one a Fluent selector on the
+/// number would pick.
+pub struct FluentPluralHelper<L> {
+    loader: L,
+}
+
+impl<L> FluentPluralHelper<L> {
+    pub fn new(loader: L) -> Self {
+        Self { loader }
+    }
+}
+
+impl<L: Loader + Send + Sync> HelperDef for FluentPluralHelper<L> {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars<'reg>,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let mismatch = || -> RenderError {
+            RenderErrorReason::ParamTypeMismatchForName(
+                "fluent-plural",
+                "0".into(),
+                "number".into(),
+            )
+            .into()
+        };
+        let value = match h.param(0).map(|p| p.value()) {
+            Some(Json::String(s)) => args::parse_number(s),
+            Some(value) => args::json_to_fluent(value),
+            None => return Err(RenderErrorReason::ParamNotFoundForIndex("fluent-plural", 0).into()),
+        };
+        let Some(FluentValue::Number(mut number)) = value else {
+            return Err(mismatch());
+        };
+        match h.hash_get("type").map(|t| t.value()) {
+            Some(Json::String(t)) if t == "cardinal" || t == "ordinal" => {
+                number.options.r#type = t.as_str().into();
+            }
+            None => {}
+            Some(_) => {
+                return Err(RenderErrorReason::ParamTypeMismatchForName(
+                    "fluent-plural",
+                    "type".into(),
+                    "\"cardinal\" or \"ordinal\"".into(),
+                )
+                .into())
+            }
+        }
+        let lang = self.loader.negotiate(&resolve_lang(h, reg, context, rcx)?);
+        let category = self.loader.plural_category(&lang, &number);
+        Ok(ScopedJson::Derived(Json::String(category.into())))
+    }
+}
+
+/// Block helper setting the scope of relative message ids, so that `.title` is looked up as
+/// `checkout-title` here:
+///
+/// ```hbs
+/// {{#fluent-scope "checkout"}}
+///     <h1>{{fluent ".title"}}</h1>
+///     {{fluent "site-name"}}
+/// {{/fluent-scope}}
+/// ```
+///
+/// A scope starting with `.` is relative to the enclosing one, and ids that don't start with
+/// `.` are used as they are.
+pub struct FluentScopeHelper;
+
+impl HelperDef for FluentScopeHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        reg: &'reg Handlebars,
+        context: &'rc Context,
+        rcx: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let scope = match h.param(0).map(|p| p.value()) {
+            Some(Json::String(scope)) => scope,
+            Some(_) => {
+                return Err(RenderErrorReason::ParamTypeMismatchForName(
+                    "fluent-scope",
+                    "0".into(),
+                    "string".into(),
+                )
+                .into())
+            }
+            None => return Err(RenderErrorReason::ParamNotFoundForIndex("fluent-scope", 0).into()),
+        };
+        let Some(template) = h.template() else {
+            return Ok(());
+        };
+        let previous = state::get(state::SCOPE, h, reg, context, rcx)?;
+        let scope = match (scope.strip_prefix('.'), &previous) {
+            (Some(relative), Some(outer)) => format!("{}-{}", outer, relative),
+            (Some(_), None) => {
+                return Err(RenderErrorReason::Other(format!(
+                    "Relative scope {} used outside of a {{{{#fluent-scope}}}} block",
+                    scope
+                ))
+                .into())
+            }
+            (None, _) => scope.clone(),
+        };
+        state::set(state::SCOPE, Some(scope), rcx);
+        let result = template.render(reg, context, rcx, out);
+        state::set(state::SCOPE, previous, rcx);
+        result
+    }
+}
+
+/// The names [`register_fluent_helpers()`] registers each helper under
+///
+/// ```rust
+/// # use handlebars_fluent::HelperNames;
+/// let names = HelperNames {
+///     fluent: "t",
+///     param: "tparam",
+///     text: "ts",
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HelperNames {
+    /// [`FluentHelper`], `fluent` by default
+    pub fluent: &'static str,
+    /// The parameter blocks of [`FluentHelper`] and [`FluentParamHelper`], `fluentparam` by
+    /// default
+    pub param: &'static str,
+    /// [`FluentLangHelper`] and [`FluentLangDecorator`], `fluent-lang` by default
+    pub lang: &'static str,
+    /// [`FluentDirHelper`], `fluent-dir` by default
+    pub dir: &'static str,
+    /// [`FluentLocalesHelper`], `each-fluent-locale` by default
+    pub locales: &'static str,
+    /// [`FluentLocaleHelper`], `fluent-locale` by default
+    pub locale: &'static str,
+    /// [`FluentScopeHelper`], `fluent-scope` by default
+    pub scope: &'static str,
+    /// [`FluentPluralHelper`], `fluent-plural` by default
+    pub plural: &'static str,
+    /// A [source-keyed](FluentHelper::source_keyed) [`FluentHelper`], `t` by default. Name it
+    /// differently when `fluent` is renamed to `t`, since `fluent` would replace it.
+    pub text: &'static str,
+}
+
+impl Default for HelperNames {
+    fn default() -> Self {
+        Self {
+            fluent: "fluent",
+            param: "fluentparam",
+            lang: "fluent-lang",
+            dir: "fluent-dir",
+            locales: "each-fluent-locale",
+            locale: "fluent-locale",
+            scope: "fluent-scope",
+            plural: "fluent-plural",
+            text: "t",
+        }
+    }
+}
+
+/// Registers every helper from this crate, sharing a single loader
+///
+/// ```rust
+/// use handlebars_fluent::*;
+/// use handlebars::*;
+///
+/// simple_loader!(create_loader, "./tests/locales/", "en-US");
+///
+/// fn init(handlebars: &mut Handlebars) {
+///     register_fluent_helpers(handlebars, create_loader(), HelperNames::default());
+/// }
+/// ```
+pub fn register_fluent_helpers<L>(handlebars: &mut Handlebars, loader: L, names: HelperNames)
+where
+    L: Loader + Send + Sync + 'static,
+{
+    let loader = Arc::new(loader);
+    handlebars.register_helper(
+        names.text,
+        Box::new(
+            FluentHelper::new(loader.clone())
+                .with_param_name(names.param)
+                .source_keyed(true),
+        ),
+    );
+    handlebars.register_helper(
+        names.fluent,
+        Box::new(FluentHelper::new(loader.clone()).with_param_name(names.param)),
+    );
+    handlebars.register_helper(names.param, Box::new(FluentParamHelper::new(names.fluent)));
+    handlebars.register_helper(names.lang, Box::new(FluentLangHelper::new(loader.clone())));
+    handlebars.register_helper(names.dir, Box::new(FluentDirHelper::new(loader.clone())));
+    handlebars.register_helper(
+        names.locales,
+        Box::new(FluentLocalesHelper::new(loader.clone())),
+    );
+    handlebars.register_helper(names.plural, Box::new(FluentPluralHelper::new(loader)));
+    handlebars.register_helper(names.locale, Box::new(FluentLocaleHelper));
+    handlebars.register_helper(names.scope, Box::new(FluentScopeHelper));
+    handlebars.register_decorator(names.lang, Box::new(FluentLangDecorator));
+}
@@ -0,0 +1,200 @@
+//! Locale-aware formatting backed by ICU4X compiled data.
+//!
+//! Formatters are memoized per bundle through the bundle's `IntlLangMemoizer`, which
+//! also tells us which locale we're formatting for.
+
+use fixed_decimal::{FixedDecimal, FloatPrecision};
+use fluent_bundle::types::{FluentNumber, FluentNumberOptions, FluentNumberStyle};
+use fluent_bundle::FluentValue;
+use icu_calendar::{DateTime, Gregorian};
+use icu_datetime::options::length;
+use icu_datetime::{TimeFormatter, TypedDateFormatter, TypedDateTimeFormatter};
+use icu_decimal::options::{FixedDecimalFormatterOptions, GroupingStrategy};
+use icu_decimal::FixedDecimalFormatter;
+use icu_list::{ListFormatter, ListLength};
+use icu_provider::DataLocale;
+use intl_memoizer::concurrent::IntlLangMemoizer;
+use intl_memoizer::Memoizable;
+use unic_langid::LanguageIdentifier;
+
+use crate::types::{
+    DateTimeStyle, FluentDateTime, FluentDateTimeOptions, FluentListOptions, ListStyle, ListType,
+};
+
+/// ICU4X falls back through its own locale data, so unknown subtags are fine here.
+fn data_locale(lang: &LanguageIdentifier) -> DataLocale {
+    lang.to_string()
+        .parse::<icu_locid::Locale>()
+        .map(DataLocale::from)
+        .unwrap_or_default()
+}
+
+/// Formatter installed with `FluentBundle::set_formatter` to format numbers
+pub(crate) fn format_value(value: &FluentValue, intls: &IntlLangMemoizer) -> Option<String> {
+    match value {
+        FluentValue::Number(n) => Some(format_number(n, intls)),
+        _ => None,
+    }
+}
+
+/// Format a number, honoring its `style`, `currency`, `useGrouping` and digit options.
+///
+/// Currencies are displayed with their ISO code, since the compiled data doesn't
+/// include currency symbols or patterns. Significant digit options are not supported.
+pub(crate) fn format_number(n: &FluentNumber, intls: &IntlLangMemoizer) -> String {
+    let opts = &n.options;
+    let (value, default_max_fraction) = match opts.style {
+        FluentNumberStyle::Decimal => (n.value, None),
+        FluentNumberStyle::Percent => (n.value * 100., Some(0)),
+        FluentNumberStyle::Currency => (n.value, Some(2)),
+    };
+    let Ok(mut decimal) = FixedDecimal::try_from_f64(value, FloatPrecision::Floating) else {
+        return n.as_string().into();
+    };
+    let max_fraction = opts.maximum_fraction_digits.or(default_max_fraction);
+    let min_fraction = match opts.style {
+        FluentNumberStyle::Currency => opts.minimum_fraction_digits.or(Some(2)),
+        _ => opts.minimum_fraction_digits,
+    };
+    round_and_pad(&mut decimal, opts, min_fraction, max_fraction);
+
+    let grouping = opts.use_grouping;
+    let formatted = intls
+        .with_try_get::<DecimalFormat, _, _>(grouping, |f| f.0.format_to_string(&decimal))
+        .unwrap_or_else(|_| decimal.to_string());
+
+    match opts.style {
+        FluentNumberStyle::Decimal => formatted,
+        FluentNumberStyle::Percent => format!("{}%", formatted),
+        FluentNumberStyle::Currency => match opts.currency {
+            Some(ref code) => format!("{}\u{a0}{}", code, formatted),
+            None => formatted,
+        },
+    }
+}
+
+fn round_and_pad(
+    decimal: &mut FixedDecimal,
+    opts: &FluentNumberOptions,
+    min_fraction: Option<usize>,
+    max_fraction: Option<usize>,
+) {
+    if let Some(max) = max_fraction {
+        decimal.half_even(-(max.max(min_fraction.unwrap_or(0)) as i16));
+    }
+    // Rounding may leave trailing zeroes behind
+    decimal.trim_end();
+    if let Some(min) = min_fraction {
+        decimal.pad_end(-(min as i16));
+    }
+    if let Some(min) = opts.minimum_integer_digits {
+        decimal.pad_start(min as i16);
+    }
+}
+
+struct DecimalFormat(FixedDecimalFormatter);
+
+impl Memoizable for DecimalFormat {
+    type Args = bool;
+    type Error = icu_decimal::DecimalError;
+
+    fn construct(lang: LanguageIdentifier, grouping: bool) -> Result<Self, Self::Error> {
+        let mut options = FixedDecimalFormatterOptions::default();
+        if !grouping {
+            options.grouping_strategy = GroupingStrategy::Never;
+        }
+        FixedDecimalFormatter::try_new(&data_locale(&lang), options).map(Self)
+    }
+}
+
+fn date_length(style: DateTimeStyle) -> length::Date {
+    match style {
+        DateTimeStyle::Full => length::Date::Full,
+        DateTimeStyle::Long => length::Date::Long,
+        DateTimeStyle::Medium => length::Date::Medium,
+        DateTimeStyle::Short => length::Date::Short,
+    }
+}
+
+// The longer time styles include a time zone name, which we don't have data for
+fn time_length(style: DateTimeStyle) -> length::Time {
+    match style {
+        DateTimeStyle::Full | DateTimeStyle::Long | DateTimeStyle::Medium => length::Time::Medium,
+        DateTimeStyle::Short => length::Time::Short,
+    }
+}
+
+pub(crate) enum DateTimeFormat {
+    Date(Box<TypedDateFormatter<Gregorian>>),
+    Time(Box<TimeFormatter>),
+    DateTime(Box<TypedDateTimeFormatter<Gregorian>>),
+    None,
+}
+
+impl DateTimeFormat {
+    /// Returns `None` if neither a date nor a time style was requested
+    pub(crate) fn format(&self, datetime: &FluentDateTime) -> Option<String> {
+        let (year, month, day, hour, minute, second) = datetime.civil();
+        let datetime =
+            DateTime::try_new_gregorian_datetime(year as i32, month, day, hour, minute, second)
+                .ok()?;
+        match self {
+            Self::Date(f) => Some(f.format_to_string(&datetime.date)),
+            Self::Time(f) => Some(f.format_to_string(&datetime)),
+            Self::DateTime(f) => Some(f.format_to_string(&datetime)),
+            Self::None => None,
+        }
+    }
+}
+
+impl Memoizable for DateTimeFormat {
+    type Args = FluentDateTimeOptions;
+    type Error = icu_datetime::DateTimeError;
+
+    fn construct(lang: LanguageIdentifier, options: Self::Args) -> Result<Self, Self::Error> {
+        let locale = data_locale(&lang);
+        Ok(match (options.date_style, options.time_style) {
+            (Some(date), Some(time)) => Self::DateTime(Box::new(TypedDateTimeFormatter::try_new(
+                &locale,
+                length::Bag::from_date_time_style(date_length(date), time_length(time)).into(),
+            )?)),
+            (Some(date), None) => Self::Date(Box::new(TypedDateFormatter::try_new_with_length(
+                &locale,
+                date_length(date),
+            )?)),
+            (None, Some(time)) => Self::Time(Box::new(TimeFormatter::try_new_with_length(
+                &locale,
+                time_length(time),
+            )?)),
+            (None, None) => Self::None,
+        })
+    }
+}
+
+pub(crate) struct ListFormat(ListFormatter);
+
+impl ListFormat {
+    pub(crate) fn format(&self, items: &[String]) -> String {
+        self.0.format_to_string(items.iter())
+    }
+}
+
+impl Memoizable for ListFormat {
+    type Args = FluentListOptions;
+    type Error = icu_list::ListError;
+
+    fn construct(lang: LanguageIdentifier, options: Self::Args) -> Result<Self, Self::Error> {
+        let locale = data_locale(&lang);
+        let length = match options.style {
+            ListStyle::Long => ListLength::Wide,
+            ListStyle::Short => ListLength::Short,
+            ListStyle::Narrow => ListLength::Narrow,
+        };
+        match options.r#type {
+            ListType::Conjunction => ListFormatter::try_new_and_with_length(&locale, length),
+            ListType::Disjunction => ListFormatter::try_new_or_with_length(&locale, length),
+            ListType::Unit => ListFormatter::try_new_unit_with_length(&locale, length),
+        }
+        .map(Self)
+    }
+}
@@ -27,9 +27,32 @@
 //! ```
 //!
 //! You should have a `locales/` folder somewhere with one folder per language code,
-//! containing all of your FTL files. See the [`simple_loader!()`] macro for more options.
+//! containing all of your FTL files. See the [`simple_loader!()`] macro for more options,
+//! including compiling the files into the binary with the help of the [`build`] module, or
+//! loading them from anywhere else through the [`source::ResourceSource`] trait.
 //!
-//! Make sure the [`handlebars::Context`] has a toplevel "lang" field when rendering.
+//! Make sure the [`handlebars::Context`] has a toplevel "lang" field when rendering, or set
+//! the locale with the [`FluentLangDecorator`] (`{{*fluent-lang "fr"}}`), which also keeps it
+//! available to partials and helpers rendering with another context. Rendering fails if no
+//! locale can be found.
+//!
+//! To register all the helpers in this crate at once, under names of your choosing, use
+//! [`register_fluent_helpers()`]:
+//!
+//! ```rust
+//! # use handlebars_fluent::*;
+//! # use handlebars::*;
+//! # simple_loader!(create_loader, "./locales/", "en-US");
+//! # fn init(handlebars: &mut Handlebars) {
+//! let names = HelperNames {
+//!     fluent: "t",
+//!     param: "tparam",
+//!     text: "ts",
+//!     ..Default::default()
+//! };
+//! register_fluent_helpers(handlebars, create_loader(), names);
+//! # }
+//! ```
 //!
 //!
 //! # Using the fluent helper in your templates
@@ -49,6 +72,26 @@
 //! {{fluent "placeholder" variable="baz"}} <!-- will render "this has a placeholder baz" -->
 //!```
 //!
+//! A `default` is rendered when no locale, including the fallback, has the message:
+//! `{{fluent "nav-sign-in" default="Sign in"}}`. The [source-keyed](FluentHelper::source_keyed)
+//! variant, registered as `t` by [`register_fluent_helpers()`], takes this text first instead,
+//! as in `{{t "Sign in" id="nav-sign-in"}}`. Without an `id`, the id is derived from the text
+//! by [`source_id()`]. Defaults are formatted as Fluent patterns with the same arguments, so
+//! `{{t "Hello { $name }" name=user.name}}` works before the message is translated. They're
+//! only used with loaders that implement [`Loader::try_lookup()`], or
+//! [`Loader::try_lookup_term()`] for terms.
+//!
+//! Ids starting with `-` refer to [terms], whose parameters can be given in the hash, as in
+//! `{{fluent "-brand" case="genitive"}}`. Only string and number parameters are passed on.
+//!
+//! With [`SimpleLoader`], ids of the form `message.attribute` refer to an [attribute] of a
+//! message, as in `{{fluent "login-input.placeholder"}}`.
+//!
+//! Ids starting with `.` are relative to the scope set by an enclosing [`FluentScopeHelper`]
+//! block, so `{{#fluent-scope "checkout"}}{{fluent ".title"}}{{/fluent-scope}}` renders
+//! `checkout-title`. See [`FluentHelper::scope_by_template()`] to scope them by template name
+//! instead.
+//!
 //! You may also use the `{{fluentparam}}` helper to specify [variables], especially if you need
 //! them to be multiline, like so:
 //!
@@ -61,9 +104,159 @@
 //! {{/fluent}}
 //! ```
 //!
-//! Multiple `{{fluentparam}}`s may be specified
+//! Multiple `{{fluentparam}}`s may be specified, and their names may come from the context
+//! (`{{#fluentparam field.name}}`). A `{{fluent}}` block may only contain `{{fluentparam}}`
+//! blocks, whitespace and comments. Register [`FluentParamHelper`] as `fluentparam` to get an
+//! error when one is used outside of a `{{fluent}}` block ([`register_fluent_helpers()`] does
+//! this).
+//!
+//! Parameter bodies are used as-is, including their indentation. With `dedent=true`, or
+//! [`FluentHelper::dedent_params()`], the indentation common to all lines is removed along
+//! with surrounding whitespace, so the example above would give `"first line\nsecond line"`.
+//!
+//! Parameter bodies, like other strings, are passed to Fluent as strings and so never select
+//! plural variants. Mark them as numbers with `type="number"`, as in
+//! `{{#fluentparam "count" type="number"}}{{cart.size}}{{/fluentparam}}`, or use
+//! `count-type="number"` for a string given in the hash. [`FluentHelper::coerce_numbers()`]
+//! converts every string that looks like a number instead.
+//!
+//! The fields of an object can be passed all at once with `args`. Nested fields are joined with
+//! `_`, so `user.address.city` becomes `$address_city` below, and `args-prefix` is put in front
+//! of every name. Other variables in the hash take precedence over the object's fields:
+//!
+//! ```hbs
+//! {{fluent "user-greeting" args=user}}
+//! {{fluent "user-greeting" args=user args-prefix="user_" user_name="you"}}
+//! ```
+//!
+//! Variables used by many messages, like the site or user name, can be passed to every
+//! message with [`FluentHelper::with_default_arg()`] and [`FluentHelper::with_context_arg()`].
+//! Variables given to the helper take precedence over these.
+//!
+//! Numeric variables can be given [`NUMBER()` options] by appending the option name to the
+//! variable name:
+//!
+//! ```hbs
+//! {{fluent "price" amount=total amount-style="currency" amount-currency="EUR" amount-minimumFractionDigits=2}}
+//! ```
+//!
+//! Without the `icu` feature only `minimumFractionDigits` affects how the number is displayed,
+//! but all options are visible to functions called from the message. Integers are passed
+//! on exactly; ones too large to be represented as a Fluent number are passed as strings.
+//!
+//! RFC 3339 strings and Unix timestamps (in seconds) can be passed as [datetimes] by marking
+//! them with a `-type` of `datetime`:
+//!
+//! ```hbs
+//! {{fluent "order-placed" when=order.created when-type="datetime" when-dateStyle="long"}}
+//! ```
+//!
+//! Datetimes are displayed at the offset they were written with, or at the offset given by
+//! `when-timeZone="+02:00"`. A default offset for every datetime in a render can be set with a
+//! toplevel "timezone" field in the [`handlebars::Context`]. Only fixed offsets (and `UTC`) are
+//! supported, not time zone names.
+//!
+//! Arrays are passed as [lists], which are joined into a conjunction ("A, B, and C") when
+//! displayed. The kind of list can be chosen with `-type` (`conjunction`, `disjunction` or
+//! `unit`) and its width with `-style` (`long`, `short` or `narrow`). For arrays of objects,
+//! `-field` selects the field to display:
+//!
+//! ```hbs
+//! {{fluent "written-by" authors=book.authors authors-field="name" authors-type="disjunction"}}
+//! ```
+//!
+//! Lists are joined with commas unless the `icu` feature is enabled, which also provides a
+//! `LIST()` function to pick the style from within the message.
+//!
+//! When the structure of a template depends on a number, [`FluentPluralHelper`] gives its
+//! plural category in the current locale: `{{#if (eq (fluent-plural n) "one")}}` (with an `eq`
+//! helper registered).
+//!
+//! With [`FluentMissingHelper`] registered as `helperMissing`, messages can also be used
+//! directly as `{{welcome-banner}}` or `{{welcome-banner name=user.name}}`.
+//!
+//! # Markup in messages
+//!
+//! Messages are output verbatim by default, including any markup in them and in their
+//! arguments. With a [`MarkupPolicy`], arguments are HTML-escaped and the markup translators
+//! write is escaped, restricted to an allow list of tags and attributes, or kept:
+//!
+//! ```rust
+//! # use handlebars_fluent::*;
+//! # simple_loader!(create_loader, "./tests/locales/", "en-US");
+//! let policy = MarkupPolicy::allow(&[("strong", &[]), ("em", &[]), ("br", &[])]);
+//! let helper = FluentHelper::new(create_loader()).with_markup_policy(policy);
+//! ```
+//!
+//! # Rendering in other locales
+//!
+//! A message can be rendered in another locale than the page's with `lang`, and everything in a
+//! [`FluentLocaleHelper`] block is rendered in the locale it's given:
+//!
+//! ```hbs
+//! {{fluent "language-name" lang="de"}}
+//! {{#fluent-locale review.lang}}{{fluent "review-by" author=review.author}}{{/fluent-locale}}
+//! ```
+//!
+//! # Locale metadata
+//!
+//! [`FluentLangHelper`] and [`FluentDirHelper`] output the locale the loader actually uses for
+//! the "lang" field, and the direction (`ltr` or `rtl`) of its script:
+//!
+//! ```rust
+//! # use handlebars_fluent::*;
+//! # use handlebars::*;
+//! # simple_loader!(create_loader, "./tests/locales/", "en-US");
+//! # fn init(handlebars: &mut Handlebars) {
+//! handlebars.register_helper("fluent-lang", Box::new(FluentLangHelper::new(create_loader())));
+//! handlebars.register_helper("fluent-dir", Box::new(FluentDirHelper::new(create_loader())));
+//! # }
+//! ```
+//!
+//! ```hbs
+//! <html lang="{{fluent-lang}}" dir="{{fluent-dir}}">
+//! ```
+//!
+//! [`FluentLocalesHelper`] iterates over the available locales, with their names and
+//! directions, to build a language switcher. A locale's native name and direction can be set
+//! in an optional `meta.toml` file in its folder, see [`LocaleMetadata`].
+//!
+//!
+//! # Helpers in messages
+//!
+//! Handlebars helpers can be made available to translators as Fluent functions, like
+//! `{ UPPER($name) }`, with [`bridge::add_helper_functions()`] in the loader's customizer.
+//! Alternatively, [`FluentHelper::render_as_template()`] renders formatted messages as
+//! Handlebars templates, giving trusted translations access to the context and all helpers.
+//!
+//!
+//! # Cargo features
+//!
+//! - `icu`: registers locale-aware `NUMBER()`, `DATETIME()` and `LIST()` functions on every
+//!   bundle, using offline [ICU4X](https://github.com/unicode-org/icu4x) data, along with
+//!   `PLATFORM()`.
+#![cfg_attr(feature = "icu", doc = "  See the [`functions`] module.")]
+#![cfg_attr(not(feature = "icu"), doc = "  See the `functions` module.")]
+//! - `markdown`: renders messages as [CommonMark](https://commonmark.org/) with
+//!   `format="markdown"`, as in `{{fluent "help-intro" format="markdown"}}`. Only the tags
+//!   CommonMark produces are kept in the result, without images; any other HTML in the message
+//!   or its arguments is escaped. Other values of `format` are passed to the message as a
+//!   variable.
+#![cfg_attr(
+    feature = "zip",
+    doc = "- `zip`: loads locales from zip archives with [`source::ZipSource`]."
+)]
+#![cfg_attr(
+    not(feature = "zip"),
+    doc = "- `zip`: loads locales from zip archives with `source::ZipSource`."
+)]
 //!
+//! [attribute]: https://projectfluent.org/fluent/guide/attributes.html
+//! [terms]: https://projectfluent.org/fluent/guide/terms.html
 //! [variables]: https://projectfluent.org/fluent/guide/variables.html
+//! [datetimes]: types::FluentDateTime
+//! [lists]: types::FluentList
+//! [`NUMBER()` options]: https://projectfluent.org/fluent/guide/functions.html#number
 //! [`simple_loader!()`]: ./macro.simple_loader.html
 
 #[doc(hidden)]
@@ -72,8 +265,26 @@ pub extern crate lazy_static;
 #[doc(hidden)]
 pub extern crate fluent_bundle;
 
-pub use helper::FluentHelper;
-pub use loader::{Loader, SimpleLoader};
+pub use helper::{
+    register_fluent_helpers, source_id, FluentDirHelper, FluentHelper, FluentLangDecorator,
+    FluentLangHelper, FluentLocaleHelper, FluentLocalesHelper, FluentMissingHelper,
+    FluentParamHelper, FluentPluralHelper, FluentScopeHelper, HelperNames,
+};
+pub use loader::{Direction, Loader, LocaleMetadata, SimpleLoader};
+pub use markup::MarkupPolicy;
 
+mod args;
+pub mod bridge;
+pub mod build;
+#[cfg(feature = "icu")]
+pub mod functions;
 mod helper;
+#[cfg(feature = "icu")]
+mod intl;
 pub mod loader;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod markup;
+pub mod source;
+mod state;
+pub mod types;
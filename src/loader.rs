@@ -1,14 +1,19 @@
 use std::collections::HashMap;
-use std::fs::read_dir;
-use std::fs::File;
-use std::io;
-use std::io::prelude::*;
-use std::path::Path;
+use std::fmt;
+use std::sync::Arc;
 
 use fluent_bundle::concurrent::FluentBundle;
-use fluent_bundle::{FluentArgs, FluentResource};
-use fluent_langneg::negotiate_languages;
+use fluent_bundle::resolver::errors::ReferenceKind;
+use fluent_bundle::resolver::ResolverError;
+use fluent_bundle::types::{FluentNumber, FluentNumberType};
+use fluent_bundle::{FluentArgs, FluentError, FluentResource, FluentValue};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use fluent_syntax::ast;
+use intl_pluralrules::{PluralCategory, PluralRuleType, PluralRules};
+use serde::Deserialize;
+use unic_langid::CharacterDirection;
 
+pub use crate::source::{CoreSource, FtlText, ResourceSource};
 pub use unic_langid::{langid, langids, LanguageIdentifier};
 
 /// Something capable of looking up Fluent keys given a language.
@@ -17,6 +22,260 @@ pub use unic_langid::{langid, langids, LanguageIdentifier};
 pub trait Loader {
     fn lookup(&self, lang: &LanguageIdentifier, text_id: &str, args: Option<&FluentArgs>)
         -> String;
+
+    /// Look up a message, returning `None` if no locale has it.
+    ///
+    /// The default implementation always returns the result of [`lookup()`](Loader::lookup).
+    fn try_lookup(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        Some(self.lookup(lang, text_id, args))
+    }
+
+    /// Look up the term `-term_id` (given without the leading `-`), passing `args` as the
+    /// term's parameters.
+    ///
+    /// The default implementation doesn't know about any terms.
+    fn lookup_term(
+        &self,
+        _lang: &LanguageIdentifier,
+        term_id: &str,
+        _args: Option<&FluentArgs>,
+    ) -> String {
+        format!("Unknown localization -{}", term_id)
+    }
+
+    /// Look up a message like [`try_lookup()`](Loader::try_lookup), also returning `None` if it
+    /// can't be formatted without errors, e.g. because an argument is missing.
+    ///
+    /// The default implementation returns the result of [`try_lookup()`](Loader::try_lookup).
+    fn try_lookup_complete(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        self.try_lookup(lang, text_id, args)
+    }
+
+    /// Look up a term, returning `None` if no locale has it.
+    ///
+    /// The default implementation always returns the result of
+    /// [`lookup_term()`](Loader::lookup_term).
+    fn try_lookup_term(
+        &self,
+        lang: &LanguageIdentifier,
+        term_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        Some(self.lookup_term(lang, term_id, args))
+    }
+
+    /// Format `text` as a Fluent pattern in `lang`, so that the default text of a missing
+    /// message can use the same placeables as a translation, like `{ $name }`.
+    ///
+    /// The default implementation returns the text as it is.
+    fn format_text(
+        &self,
+        _lang: &LanguageIdentifier,
+        text: &str,
+        _args: Option<&FluentArgs>,
+    ) -> String {
+        text.to_string()
+    }
+
+    /// The locale that strings for `lang` are actually taken from.
+    ///
+    /// Defaults to `lang` itself.
+    fn negotiate(&self, lang: &LanguageIdentifier) -> LanguageIdentifier {
+        lang.clone()
+    }
+
+    /// The CLDR plural category (`zero`, `one`, `two`, `few`, `many` or `other`) of a number
+    /// in `lang`, using ordinal rules if the number's `type` option is `ordinal`
+    fn plural_category(&self, lang: &LanguageIdentifier, number: &FluentNumber) -> &'static str {
+        let rule_type = match number.options.r#type {
+            FluentNumberType::Cardinal => PluralRuleType::CARDINAL,
+            FluentNumberType::Ordinal => PluralRuleType::ORDINAL,
+        };
+        PluralRules::create(lang.clone(), rule_type)
+            .and_then(|rules| rules.select(number))
+            .map(category_name)
+            .unwrap_or("other")
+    }
+
+    /// Metadata provided for a locale, if any
+    fn metadata(&self, _lang: &LanguageIdentifier) -> Option<&LocaleMetadata> {
+        None
+    }
+
+    /// The locales strings are available in, e.g. for a language switcher
+    fn locales(&self) -> Vec<LanguageIdentifier> {
+        Vec::new()
+    }
+
+    /// The name of a locale in its own language
+    fn native_name(&self, lang: &LanguageIdentifier) -> Option<String> {
+        self.metadata(lang)?.native_name.clone()
+    }
+
+    /// The name of the locale `of` in the language `lang`
+    fn display_name(&self, _lang: &LanguageIdentifier, _of: &LanguageIdentifier) -> Option<String> {
+        None
+    }
+
+    /// The script direction of a locale, taken from its metadata if set there
+    fn direction(&self, lang: &LanguageIdentifier) -> Direction {
+        self.metadata(lang)
+            .and_then(|meta| meta.dir)
+            .unwrap_or_else(|| Direction::of(lang))
+    }
+}
+
+impl<L: Loader + ?Sized> Loader for Arc<L> {
+    fn lookup(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> String {
+        (**self).lookup(lang, text_id, args)
+    }
+
+    fn try_lookup(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        (**self).try_lookup(lang, text_id, args)
+    }
+
+    fn lookup_term(
+        &self,
+        lang: &LanguageIdentifier,
+        term_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> String {
+        (**self).lookup_term(lang, term_id, args)
+    }
+
+    fn try_lookup_complete(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        (**self).try_lookup_complete(lang, text_id, args)
+    }
+
+    fn try_lookup_term(
+        &self,
+        lang: &LanguageIdentifier,
+        term_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        (**self).try_lookup_term(lang, term_id, args)
+    }
+
+    fn format_text(
+        &self,
+        lang: &LanguageIdentifier,
+        text: &str,
+        args: Option<&FluentArgs>,
+    ) -> String {
+        (**self).format_text(lang, text, args)
+    }
+
+    fn negotiate(&self, lang: &LanguageIdentifier) -> LanguageIdentifier {
+        (**self).negotiate(lang)
+    }
+
+    fn plural_category(&self, lang: &LanguageIdentifier, number: &FluentNumber) -> &'static str {
+        (**self).plural_category(lang, number)
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> Option<&LocaleMetadata> {
+        (**self).metadata(lang)
+    }
+
+    fn direction(&self, lang: &LanguageIdentifier) -> Direction {
+        (**self).direction(lang)
+    }
+
+    fn locales(&self) -> Vec<LanguageIdentifier> {
+        (**self).locales()
+    }
+
+    fn native_name(&self, lang: &LanguageIdentifier) -> Option<String> {
+        (**self).native_name(lang)
+    }
+
+    fn display_name(&self, lang: &LanguageIdentifier, of: &LanguageIdentifier) -> Option<String> {
+        (**self).display_name(lang, of)
+    }
+}
+
+fn category_name(category: PluralCategory) -> &'static str {
+    match category {
+        PluralCategory::ZERO => "zero",
+        PluralCategory::ONE => "one",
+        PluralCategory::TWO => "two",
+        PluralCategory::FEW => "few",
+        PluralCategory::MANY => "many",
+        PluralCategory::OTHER => "other",
+    }
+}
+
+/// The direction a locale's script is written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+impl Direction {
+    /// The direction of a locale's (possibly implied) script. Vertical scripts are
+    /// treated as left-to-right, since that's how HTML lays them out by default.
+    pub fn of(lang: &LanguageIdentifier) -> Self {
+        match lang.character_direction() {
+            CharacterDirection::RTL => Direction::Rtl,
+            _ => Direction::Ltr,
+        }
+    }
+
+    /// The value to use for an HTML `dir` attribute
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Optional information about a locale, read from a `meta.toml` file in its folder:
+///
+/// ```toml
+/// native-name = "עברית"
+/// dir = "rtl"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LocaleMetadata {
+    /// The name of the locale in its own language
+    pub native_name: Option<String>,
+    /// Overrides the direction derived from the locale's script
+    pub dir: Option<Direction>,
 }
 
 /// Loads Fluent data at runtime via `lazy_static` to produce a loader.
@@ -35,8 +294,8 @@ pub trait Loader {
 /// ```
 ///
 /// `$constructor` is the name of the constructor function for the loader, `$location` is
-/// the location of a folder containing individual locale folders, `$fallback` is the language to use
-/// for fallback strings.
+/// the location of a folder containing individual locale folders, or any other
+/// [`ResourceSource`], and `$fallback` is the language to use for fallback strings.
 ///
 /// Some Fluent users have a share "core.ftl" file that contains strings used by all locales,
 /// for example branding information. They also may want to define custom functions on the bundle.
@@ -47,7 +306,7 @@ pub trait Loader {
 /// use handlebars_fluent::*;
 ///
 /// simple_loader!(create_loader, "./tests/locales/", "en-US", core: "./tests/core.ftl",
-///                customizer: |bundle| {bundle.add_function("FOOBAR", |_values, _named| {unimplemented!()}); });
+///                customizer: |bundle, _lang| {bundle.add_function("FOOBAR", |_values, _named| {unimplemented!()}); });
 ///
 /// fn init() {
 ///     let loader = create_loader();
@@ -55,33 +314,103 @@ pub trait Loader {
 /// }
 /// ```
 ///
+/// The customizer is called once per bundle, with the locale the bundle is being built for,
+/// so functions and settings can differ between locales. With the `icu` feature, the
+#[cfg_attr(
+    feature = "icu",
+    doc = "[built-in functions](crate::functions) have already been added at this point."
+)]
+#[cfg_attr(
+    not(feature = "icu"),
+    doc = "built-in functions have already been added at this point."
+)]
+///
+/// Core strings that differ per locale can be put in a separate folder with one folder per
+/// locale code, in the same layout as `$location`. These are added after the shared core file,
+/// and override any of its entries:
+///
+/// ```rust
+/// use handlebars_fluent::*;
+///
+/// simple_loader!(create_loader, "./tests/locales/", "en-US", core: "./tests/core.ftl",
+///                locale_core: "./tests/core/",
+///                customizer: |bundle, lang| {
+///                    if lang.language == "fr" {
+///                        bundle.set_use_isolating(true);
+///                    }
+///                });
+/// ```
+///
+/// `core:` takes a file path, or FTL text wrapped in [`FtlText`], e.g. to compile it into the
+/// binary with `include_str!()`. It can be left out when there's no shared core file:
+///
+/// ```rust
+/// use handlebars_fluent::*;
+///
+/// simple_loader!(create_loader, "./tests/locales/", "en-US", locale_core: "./tests/core/",
+///                customizer: |_bundle, _lang| {});
+/// ```
+///
+/// A locale folder may also contain a `meta.toml` file with [metadata](LocaleMetadata) about the
+/// locale.
+///
+/// Locale names are taken from FTL files if not set in the metadata: the native name of a locale
+/// from a `-language-name` term in its own files, and the name of another locale `xx-YY` from a
+/// `language-name-xx-YY` message.
+///
+/// The locales can also be compiled into the binary, so that it doesn't depend on the working
+/// directory it runs from. Generate the list of files from a build script with
+/// [`build::embed_locales()`](crate::build::embed_locales), and include it with
+/// `embedded:`. The files are only parsed when the loader is first used.
+///
+/// ```rust,ignore
+/// use handlebars_fluent::*;
+///
+/// simple_loader!(create_loader, embedded: include!(concat!(env!("OUT_DIR"), "/locales.rs")), "en-US");
+/// ```
+///
+/// A customizer can be given after the fallback language, as in
+/// `simple_loader!(create_loader, embedded: …, "en-US", customizer: |bundle, lang| { … })`.
+///
 /// The constructor function is cheap to call multiple times since all the heavy duty stuff is stored in shared statics.
 ///
 #[macro_export]
 macro_rules! simple_loader {
+    ($constructor:ident, embedded: $embedded:expr, $fallback:expr) => {
+        $crate::simple_loader!(@loader $constructor, $embedded, $fallback, None::<&str>, None::<&str>, |_bundle, _lang| {});
+    };
+    ($constructor:ident, embedded: $embedded:expr, $fallback:expr, customizer: $custom:expr) => {
+        $crate::simple_loader!(@loader $constructor, $embedded, $fallback, None::<&str>, None::<&str>, $custom);
+    };
     ($constructor:ident, $location:expr, $fallback:expr) => {
-        $crate::lazy_static::lazy_static! {
-            static ref RESOURCES: std::collections::HashMap<$crate::loader::LanguageIdentifier, Vec<$crate::fluent_bundle::FluentResource>> = $crate::loader::build_resources($location);
-            static ref BUNDLES: std::collections::HashMap<$crate::loader::LanguageIdentifier, $crate::fluent_bundle::concurrent::FluentBundle<&'static $crate::fluent_bundle::FluentResource>> = $crate::loader::build_bundles(&&RESOURCES, None, |_bundle| {});
-            static ref LOCALES: Vec<$crate::loader::LanguageIdentifier> = RESOURCES.keys().cloned().collect();
-            static ref FALLBACKS: std::collections::HashMap<$crate::loader::LanguageIdentifier, Vec<$crate::loader::LanguageIdentifier>> = $crate::loader::build_fallbacks(&*LOCALES);
-        }
-
-        pub fn $constructor() -> $crate::loader::SimpleLoader {
-            $crate::loader::SimpleLoader::new(&*BUNDLES, &*FALLBACKS, $fallback.parse().expect("fallback language not valid"))
-        }
+        $crate::simple_loader!(@loader $constructor, $location, $fallback, None::<&str>, None::<&str>, |_bundle, _lang| {});
     };
     ($constructor:ident, $location:expr, $fallback:expr, core: $core:expr, customizer: $custom:expr) => {
+        $crate::simple_loader!(@loader $constructor, $location, $fallback, Some($core), None::<&str>, $custom);
+    };
+    ($constructor:ident, $location:expr, $fallback:expr, locale_core: $locale_core:expr, customizer: $custom:expr) => {
+        $crate::simple_loader!(@loader $constructor, $location, $fallback, None::<&str>, Some($locale_core), $custom);
+    };
+    ($constructor:ident, $location:expr, $fallback:expr, core: $core:expr, locale_core: $locale_core:expr, customizer: $custom:expr) => {
+        $crate::simple_loader!(@loader $constructor, $location, $fallback, Some($core), Some($locale_core), $custom);
+    };
+    (@loader $constructor:ident, $source:expr, $fallback:expr, $core:expr, $locale_core:expr, $custom:expr) => {
         $crate::lazy_static::lazy_static! {
-            static ref CORE_RESOURCE: $crate::fluent_bundle::FluentResource = $crate::loader::load_core_resource($core);
-            static ref RESOURCES: std::collections::HashMap<$crate::loader::LanguageIdentifier, Vec<$crate::fluent_bundle::FluentResource>> = $crate::loader::build_resources($location);
-            static ref BUNDLES: std::collections::HashMap<$crate::loader::LanguageIdentifier, $crate::fluent_bundle::concurrent::FluentBundle<&'static $crate::fluent_bundle::FluentResource>> = $crate::loader::build_bundles(&*RESOURCES, Some(&CORE_RESOURCE), $custom);
-            static ref LOCALES: Vec<$crate::loader::LanguageIdentifier> = RESOURCES.keys().cloned().collect();
+            static ref CORE_RESOURCE: Option<$crate::fluent_bundle::FluentResource> = $core.map($crate::loader::load_core_resource);
+            static ref LOCALE_CORE_RESOURCES: std::collections::HashMap<$crate::loader::LanguageIdentifier, Vec<$crate::fluent_bundle::FluentResource>> = $locale_core.map($crate::loader::build_resources).unwrap_or_default();
+            // The source is only evaluated once, and dropped once both are read from it
+            static ref SOURCE: (std::collections::HashMap<$crate::loader::LanguageIdentifier, Vec<$crate::fluent_bundle::FluentResource>>, std::collections::HashMap<$crate::loader::LanguageIdentifier, $crate::loader::LocaleMetadata>) = {
+                let source = $source;
+                ($crate::loader::build_resources(&source), $crate::loader::build_metadata(&source))
+            };
+            static ref BUNDLES: std::collections::HashMap<$crate::loader::LanguageIdentifier, $crate::fluent_bundle::concurrent::FluentBundle<&'static $crate::fluent_bundle::FluentResource>> = $crate::loader::build_bundles(&SOURCE.0, CORE_RESOURCE.as_ref(), &*LOCALE_CORE_RESOURCES, $custom);
+            static ref LOCALES: Vec<$crate::loader::LanguageIdentifier> = SOURCE.0.keys().cloned().collect();
             static ref FALLBACKS: std::collections::HashMap<$crate::loader::LanguageIdentifier, Vec<$crate::loader::LanguageIdentifier>> = $crate::loader::build_fallbacks(&*LOCALES);
         }
 
         pub fn $constructor() -> $crate::loader::SimpleLoader {
             $crate::loader::SimpleLoader::new(&*BUNDLES, &*FALLBACKS, $fallback.parse().expect("fallback language not valid"))
+                .with_metadata(&SOURCE.1)
         }
     };
 }
@@ -115,6 +444,7 @@ pub struct SimpleLoader {
     bundles: &'static HashMap<LanguageIdentifier, FluentBundle<&'static FluentResource>>,
     fallbacks: &'static HashMap<LanguageIdentifier, Vec<LanguageIdentifier>>,
     fallback: LanguageIdentifier,
+    metadata: Option<&'static HashMap<LanguageIdentifier, LocaleMetadata>>,
 }
 
 impl SimpleLoader {
@@ -130,29 +460,61 @@ impl SimpleLoader {
             bundles,
             fallbacks,
             fallback,
+            metadata: None,
         }
     }
 
-    /// Convenience function to look up a string for a single language
+    /// Use per-locale metadata, as loaded by [`build_metadata()`]
+    pub fn with_metadata(
+        mut self,
+        metadata: &'static HashMap<LanguageIdentifier, LocaleMetadata>,
+    ) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Convenience function to look up a string for a single language. Ids of the form
+    /// `message.attribute` look up an attribute of the message.
     pub fn lookup_single_language(
         &self,
         lang: &LanguageIdentifier,
         text_id: &str,
         args: Option<&FluentArgs>,
     ) -> Option<String> {
+        match self.format_single_language(lang, text_id, args)? {
+            Ok(value) => Some(value),
+            Err(errors) => panic!(
+                "Failed to format a message for locale {} and id {}.\nErrors\n{:?}",
+                lang, text_id, errors
+            ),
+        }
+    }
+
+    /// Format a message for a single language, returning the errors instead of panicking
+    fn format_single_language(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<Result<String, Vec<FluentError>>> {
         if let Some(bundle) = self.bundles.get(lang) {
-            if let Some(message) = bundle.get_message(text_id).and_then(|m| m.value()) {
+            // `message.attribute` refers to an attribute of the message
+            let pattern = match text_id.split_once('.') {
+                Some((id, attribute)) => bundle
+                    .get_message(id)
+                    .and_then(|m| m.get_attribute(attribute))
+                    .map(|a| a.value()),
+                None => bundle.get_message(text_id).and_then(|m| m.value()),
+            };
+            if let Some(message) = pattern {
                 let mut errors = Vec::new();
 
                 let value = bundle.format_pattern(message, args, &mut errors);
 
                 if errors.is_empty() {
-                    Some(value.into())
+                    Some(Ok(value.into()))
                 } else {
-                    panic!(
-                        "Failed to format a message for locale {} and id {}.\nErrors\n{:?}",
-                        lang, text_id, errors
-                    )
+                    Some(Err(errors))
                 }
             } else {
                 None
@@ -162,6 +524,26 @@ impl SimpleLoader {
         }
     }
 
+    /// Convenience function to look up a term (without its leading `-`) for a single language
+    pub fn lookup_term_single_language(
+        &self,
+        lang: &LanguageIdentifier,
+        term_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        if let Some(bundle) = self.bundles.get(lang) {
+            match format_term(bundle, term_id, args) {
+                Ok(value) => value,
+                Err(errors) => panic!(
+                    "Failed to format a term for locale {} and id -{}.\nErrors\n{:?}",
+                    lang, term_id, errors
+                ),
+            }
+        } else {
+            panic!("Unknown language {}", lang)
+        }
+    }
+
     /// Convenience function to look up a string without falling back to the default fallback language
     pub fn lookup_no_default_fallback(
         &self,
@@ -187,103 +569,383 @@ impl Loader for SimpleLoader {
         text_id: &str,
         args: Option<&FluentArgs>,
     ) -> String {
+        self.try_lookup(lang, text_id, args)
+            .unwrap_or_else(|| format!("Unknown localization {}", text_id))
+    }
+
+    fn try_lookup(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        // Locales we have no strings for use the closest one instead of panicking
+        let lang = &self.negotiate(lang);
+        if let Some(val) = self.lookup_no_default_fallback(lang, text_id, args) {
+            return Some(val);
+        }
+        if *lang != self.fallback {
+            return self.lookup_single_language(&self.fallback, text_id, args);
+        }
+        None
+    }
+
+    fn lookup_term(
+        &self,
+        lang: &LanguageIdentifier,
+        term_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> String {
+        self.try_lookup_term(lang, term_id, args)
+            .unwrap_or_else(|| format!("Unknown localization -{}", term_id))
+    }
+
+    fn try_lookup_complete(
+        &self,
+        lang: &LanguageIdentifier,
+        text_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        let lang = &self.negotiate(lang);
+        let fallback = Some(&self.fallback).filter(|fallback| *fallback != lang);
+        self.fallbacks
+            .get(lang)
+            .expect("language not found")
+            .iter()
+            .chain(fallback)
+            .find_map(|l| self.format_single_language(l, text_id, args))?
+            .ok()
+    }
+
+    // Same fallbacks as for messages
+    fn try_lookup_term(
+        &self,
+        lang: &LanguageIdentifier,
+        term_id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        let lang = &self.negotiate(lang);
         for l in self.fallbacks.get(lang).expect("language not found") {
-            if let Some(val) = self.lookup_single_language(l, text_id, args) {
-                return val;
+            if let Some(val) = self.lookup_term_single_language(l, term_id, args) {
+                return Some(val);
             }
         }
         if *lang != self.fallback {
-            if let Some(val) = self.lookup_single_language(&self.fallback, text_id, args) {
-                return val;
+            return self.lookup_term_single_language(&self.fallback, term_id, args);
+        }
+        None
+    }
+
+    // Text that isn't valid FTL is used as it is. Fluent trims the whitespace around a
+    // pattern, so that is added back.
+    fn format_text(
+        &self,
+        lang: &LanguageIdentifier,
+        text: &str,
+        args: Option<&FluentArgs>,
+    ) -> String {
+        let pattern = text.trim();
+        let Some(bundle) = self.bundles.get(&self.negotiate(lang)) else {
+            return text.to_string();
+        };
+        if pattern.is_empty() {
+            return text.to_string();
+        }
+        let start = text.len() - text.trim_start().len();
+        let (leading, trailing) = (&text[..start], &text[start + pattern.len()..]);
+        let source = format!("text = {}\n", pattern.replace('\n', "\n    "));
+        let Ok(resource) = FluentResource::try_new(source) else {
+            return text.to_string();
+        };
+        let formatted = match resource.entries().next() {
+            Some(ast::Entry::Message(ast::Message {
+                value: Some(ref value),
+                ..
+            })) => {
+                let mut errors = Vec::new();
+                let value = bundle.format_pattern(value, args, &mut errors);
+                format!("{}{}{}", leading, value, trailing)
             }
+            _ => text.to_string(),
+        };
+        formatted
+    }
+
+    // Let the bundle select the variant, so that this is exactly what a selector would pick
+    fn plural_category(&self, lang: &LanguageIdentifier, number: &FluentNumber) -> &'static str {
+        lazy_static::lazy_static! {
+            static ref SELECTOR: FluentResource = FluentResource::try_new(
+                "category = { $n ->\n    [zero] zero\n    [one] one\n    [two] two\n    [few] few\n    [many] many\n   *[other] other\n}\n"
+                    .to_string()
+            )
+            .expect("plural selector did not parse");
         }
-        format!("Unknown localization {}", text_id)
+        let Some(bundle) = self
+            .bundles
+            .get(lang)
+            .or_else(|| self.bundles.get(&self.fallback))
+        else {
+            return "other";
+        };
+        let pattern = match SELECTOR.entries().next() {
+            Some(ast::Entry::Message(ast::Message {
+                value: Some(ref value),
+                ..
+            })) => value,
+            _ => unreachable!(),
+        };
+        let mut args = FluentArgs::new();
+        args.set("n", FluentValue::Number(number.clone()));
+        let mut errors = Vec::new();
+        let category = bundle.format_pattern(pattern, Some(&args), &mut errors);
+        ["zero", "one", "two", "few", "many"]
+            .iter()
+            .copied()
+            .find(|c| *c == category)
+            .unwrap_or("other")
     }
-}
 
-fn read_from_file<P: AsRef<Path>>(filename: P) -> io::Result<FluentResource> {
-    let mut file = File::open(filename)?;
-    let mut string = String::new();
+    // The closest locale we have strings for, or the fallback language
+    fn negotiate(&self, lang: &LanguageIdentifier) -> LanguageIdentifier {
+        if self.bundles.contains_key(lang) {
+            return lang.clone();
+        }
+        let available: Vec<&LanguageIdentifier> = self.bundles.keys().collect();
+        let fallback = &self.fallback;
+        negotiate_languages(
+            &[lang],
+            &available,
+            Some(&fallback),
+            NegotiationStrategy::Lookup,
+        )
+        .first()
+        .map(|l| (**l).clone())
+        .unwrap_or_else(|| self.fallback.clone())
+    }
 
-    file.read_to_string(&mut string)?;
+    fn metadata(&self, lang: &LanguageIdentifier) -> Option<&LocaleMetadata> {
+        self.metadata?.get(lang)
+    }
 
-    Ok(FluentResource::try_new(string).expect("File did not parse!"))
+    fn locales(&self) -> Vec<LanguageIdentifier> {
+        let mut locales: Vec<_> = self.bundles.keys().cloned().collect();
+        locales.sort_by_key(|l| l.to_string());
+        locales
+    }
+
+    // Metadata takes precedence over the `-language-name` term
+    fn native_name(&self, lang: &LanguageIdentifier) -> Option<String> {
+        if let Some(name) = self.metadata(lang).and_then(|m| m.native_name.clone()) {
+            return Some(name);
+        }
+        self.bundles.get(lang)?;
+        self.lookup_term_single_language(lang, "language-name", None)
+    }
+
+    fn display_name(&self, lang: &LanguageIdentifier, of: &LanguageIdentifier) -> Option<String> {
+        if !self.fallbacks.contains_key(lang) {
+            return None;
+        }
+        self.lookup_no_default_fallback(lang, &format!("language-name-{}", of), None)
+    }
 }
 
-fn read_from_dir<P: AsRef<Path>>(dirname: P) -> io::Result<Vec<FluentResource>> {
-    let mut result = Vec::new();
-    for dir_entry in read_dir(dirname)? {
-        let entry = dir_entry?;
+/// Formats a term by resolving a reference to it, since bundles don't expose terms directly.
+///
+/// Returns `Ok(None)` if the bundle doesn't have the term.
+fn format_term(
+    bundle: &FluentBundle<&'static FluentResource>,
+    id: &str,
+    args: Option<&FluentArgs>,
+) -> Result<Option<String>, Vec<FluentError>> {
+    let source = format!("term = {{ -{}{} }}", id, term_arguments(args));
+    let Ok(resource) = FluentResource::try_new(source) else {
+        return Ok(None);
+    };
+    let pattern = match resource.entries().next() {
+        Some(ast::Entry::Message(ast::Message {
+            value: Some(ref value),
+            ..
+        })) => value,
+        _ => return Ok(None),
+    };
+    let mut errors = Vec::new();
+    let value = bundle.format_pattern(pattern, None, &mut errors);
+    let missing = |e: &FluentError| {
+        matches!(
+            e,
+            FluentError::ResolverError(ResolverError::Reference(ReferenceKind::Term {
+                id: missing,
+                attribute: None,
+            })) if missing == id
+        )
+    };
+    if errors.is_empty() {
+        Ok(Some(value.into()))
+    } else if errors.iter().any(missing) {
+        Ok(None)
+    } else {
+        Err(errors)
+    }
+}
 
-        // Prevent loading non-FTL files as translations, such as VIM temporary files.
-        if entry.path().extension().and_then(|e| e.to_str()) != Some("ftl") {
+/// Writes arguments as FTL call arguments, like `(case: "genitive", count: 3)`.
+///
+/// Terms only take literals, so arguments that aren't strings or numbers are left out, as
+/// are ones whose names aren't valid identifiers.
+fn term_arguments(args: Option<&FluentArgs>) -> String {
+    let Some(args) = args else {
+        return String::new();
+    };
+    let mut written = Vec::new();
+    for (name, value) in args.iter() {
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
             continue;
         }
-
-        let resource = read_from_file(entry.path())?;
-        result.push(resource);
+        let literal = match value {
+            FluentValue::String(s) => {
+                let mut literal = String::from("\"");
+                for c in s.chars() {
+                    match c {
+                        '"' => literal.push_str("\\\""),
+                        '\\' => literal.push_str("\\\\"),
+                        '\n' => literal.push_str("\\u000A"),
+                        '\r' => literal.push_str("\\u000D"),
+                        c => literal.push(c),
+                    }
+                }
+                literal.push('"');
+                literal
+            }
+            FluentValue::Number(n) => n.as_string().into_owned(),
+            _ => continue,
+        };
+        written.push(format!("{}: {}", name, literal));
+    }
+    if written.is_empty() {
+        String::new()
+    } else {
+        format!("({})", written.join(", "))
     }
-    Ok(result)
 }
 
 pub fn create_bundle(
     lang: LanguageIdentifier,
     resources: &'static [FluentResource],
     core_resource: Option<&'static FluentResource>,
-    customizer: &impl Fn(&mut FluentBundle<&'static FluentResource>),
+    locale_core_resources: &'static [FluentResource],
+    customizer: &impl Fn(&mut FluentBundle<&'static FluentResource>, &LanguageIdentifier),
 ) -> FluentBundle<&'static FluentResource> {
     let mut bundle: FluentBundle<&'static FluentResource> =
-        FluentBundle::new_concurrent([lang].to_vec());
+        FluentBundle::new_concurrent([lang.clone()].to_vec());
 
     // handlebars variables may be used for URLs/etc as well
     bundle.set_use_isolating(false);
+    #[cfg(feature = "icu")]
+    crate::functions::add_builtin_functions(&mut bundle)
+        .expect("Failed to add builtin functions to the bundle");
     if let Some(core) = core_resource {
         bundle
             .add_resource(core)
             .expect("Failed to add core resource to bundle");
     }
+    // Locale-specific core strings take precedence over the shared ones
+    for res in locale_core_resources {
+        bundle.add_resource_overriding(res);
+    }
     for res in resources {
         bundle
             .add_resource(res)
             .expect("Failed to add FTL resources to the bundle.");
     }
 
-    customizer(&mut bundle);
+    customizer(&mut bundle, &lang);
     bundle
 }
 
-pub fn build_resources(dir: &str) -> HashMap<LanguageIdentifier, Vec<FluentResource>> {
+/// Parses the FTL sources of every locale in `source`, which can be a folder, embedded files or
+/// any other [`ResourceSource`]
+pub fn build_resources(
+    source: impl ResourceSource,
+) -> HashMap<LanguageIdentifier, Vec<FluentResource>> {
     let mut all_resources = HashMap::new();
-    let entries = read_dir(dir).unwrap();
-    for entry in entries {
-        let entry = entry.unwrap();
-        if entry.file_type().unwrap().is_dir() {
-            if let Ok(lang) = entry.file_name().into_string() {
-                let resources = read_from_dir(entry.path()).unwrap();
-                all_resources.insert(lang.parse().unwrap(), resources);
-            }
-        }
+    let locales = source
+        .locales()
+        .unwrap_or_else(|e| panic!("Failed to list the locales: {}", e));
+    for lang in locales {
+        let resources = source
+            .resources(&lang)
+            .unwrap_or_else(|e| panic!("Failed to load the resources of {}: {}", lang, e))
+            .into_iter()
+            .map(|(name, source)| {
+                FluentResource::try_new(source)
+                    .unwrap_or_else(|_| panic!("File {} did not parse!", name))
+            })
+            .collect();
+        all_resources.insert(lang, resources);
     }
     all_resources
 }
 
+/// Reads the `meta.toml` files of the locales in `source`. Locales without one are left out.
+pub fn build_metadata(source: impl ResourceSource) -> HashMap<LanguageIdentifier, LocaleMetadata> {
+    let mut all_metadata = HashMap::new();
+    let locales = source
+        .locales()
+        .unwrap_or_else(|e| panic!("Failed to list the locales: {}", e));
+    for lang in locales {
+        let metadata = source
+            .metadata(&lang)
+            .unwrap_or_else(|e| panic!("Failed to load the metadata of {}: {}", lang, e));
+        if let Some(metadata) = metadata {
+            let metadata = toml::from_str(&metadata)
+                .unwrap_or_else(|e| panic!("Invalid locale metadata for {}: {}", lang, e));
+            all_metadata.insert(lang, metadata);
+        }
+    }
+    all_metadata
+}
+
+/// The FTL files and metadata of a locale compiled into the binary, as generated by
+/// [`build::embed_locales()`](crate::build::embed_locales)
+#[derive(Clone, Copy, Debug)]
+pub struct EmbeddedLocale {
+    /// The locale code, e.g. `en-US`
+    pub lang: &'static str,
+    /// The contents of each FTL file
+    pub resources: &'static [&'static str],
+    /// The contents of the locale's `meta.toml` file, if it has one
+    pub metadata: Option<&'static str>,
+}
+
 pub fn build_bundles(
     resources: &'static HashMap<LanguageIdentifier, Vec<FluentResource>>,
     core_resource: Option<&'static FluentResource>,
-    customizer: impl Fn(&mut FluentBundle<&'static FluentResource>),
+    locale_core_resources: &'static HashMap<LanguageIdentifier, Vec<FluentResource>>,
+    customizer: impl Fn(&mut FluentBundle<&'static FluentResource>, &LanguageIdentifier),
 ) -> HashMap<LanguageIdentifier, FluentBundle<&'static FluentResource>> {
     let mut bundles = HashMap::new();
     for (k, v) in resources.iter() {
+        let locale_core = locale_core_resources
+            .get(k)
+            .map(|r| &r[..])
+            .unwrap_or_default();
         bundles.insert(
             k.clone(),
-            create_bundle(k.clone(), v, core_resource, &customizer),
+            create_bundle(k.clone(), v, core_resource, locale_core, &customizer),
         );
     }
     bundles
 }
 
-pub fn load_core_resource(path: &str) -> FluentResource {
-    read_from_file(path).expect("cannot find core resource")
+/// Reads the FTL shared by all locales from `source`, a file path or [`FtlText`]
+pub fn load_core_resource(source: impl CoreSource) -> FluentResource {
+    let source = source.core().expect("cannot find core resource");
+    FluentResource::try_new(source).expect("File did not parse!")
 }
 
 #[cfg(test)]
@@ -300,10 +962,13 @@ mod tests {
         std::fs::write(dir.path().join("invalid.txt"), "baz = foo\n".as_bytes())?;
         std::fs::write(dir.path().join(".binary_file.swp"), [0, 1, 2, 3, 4, 5])?;
 
-        let result = read_from_dir(dir.path())?;
+        let result: Vec<_> = crate::source::ftl_files(dir.path())?
+            .into_iter()
+            .map(|(_, source)| FluentResource::try_new(source).unwrap())
+            .collect();
         assert_eq!(2, result.len()); // Doesn't include the binary file or the txt file
 
-        let mut bundle = FluentBundle::new_concurrent((&[unic_langid::langid!("en-US")]).to_vec());
+        let mut bundle = FluentBundle::new_concurrent([unic_langid::langid!("en-US")].to_vec());
         for resource in &result {
             bundle.add_resource(resource).unwrap();
         }
@@ -332,4 +997,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_default_plural_category() {
+        struct NoStrings;
+
+        impl Loader for NoStrings {
+            fn lookup(&self, _: &LanguageIdentifier, _: &str, _: Option<&FluentArgs>) -> String {
+                String::new()
+            }
+        }
+
+        let en = unic_langid::langid!("en");
+        assert_eq!(
+            "one",
+            NoStrings.plural_category(&en, &FluentNumber::from(1))
+        );
+        assert_eq!(
+            "other",
+            NoStrings.plural_category(&en, &FluentNumber::from(2))
+        );
+        let mut second = FluentNumber::from(2);
+        second.options.r#type = FluentNumberType::Ordinal;
+        assert_eq!("two", NoStrings.plural_category(&en, &second));
+    }
 }
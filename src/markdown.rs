@@ -0,0 +1,36 @@
+//! CommonMark rendering of messages, enabled with the `markdown` feature.
+
+use pulldown_cmark::{html, Options, Parser};
+
+use crate::MarkupPolicy;
+
+lazy_static::lazy_static! {
+    /// The tags CommonMark produces, except for images
+    static ref POLICY: MarkupPolicy = MarkupPolicy::allow(&[
+        ("p", &[]),
+        ("h1", &[]),
+        ("h2", &[]),
+        ("h3", &[]),
+        ("h4", &[]),
+        ("h5", &[]),
+        ("h6", &[]),
+        ("blockquote", &[]),
+        ("pre", &[]),
+        ("code", &["class"]),
+        ("ul", &[]),
+        ("ol", &["start"]),
+        ("li", &[]),
+        ("em", &[]),
+        ("strong", &[]),
+        ("a", &["href", "title"]),
+        ("br", &[]),
+        ("hr", &[]),
+    ]);
+}
+
+/// Render `text` as CommonMark, keeping only the HTML that CommonMark itself produces
+pub(crate) fn render(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut out, Parser::new_ext(text, Options::empty()));
+    POLICY.apply(&out)
+}
@@ -0,0 +1,332 @@
+//! Policies for the HTML markup translators write in messages.
+
+use std::collections::{HashMap, HashSet};
+
+/// Attributes holding URLs, which are dropped if they use a scheme other than these
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "action", "formaction", "cite", "poster"];
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto", "tel"];
+
+/// What to do with HTML markup in formatted messages, see
+/// [`FluentHelper::with_markup_policy()`](crate::FluentHelper::with_markup_policy).
+///
+/// Entity references like `&nbsp;` are kept under every policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkupPolicy {
+    /// Escape all markup, so that `<b>` is displayed as text
+    Escape,
+    /// Keep the tags in the map, with only the attributes listed for them, and escape other
+    /// tags. URL attributes are dropped unless they're relative or use `http`, `https`,
+    /// `mailto` or `tel`.
+    AllowList(HashMap<String, HashSet<String>>),
+    /// Keep all markup
+    AllowAll,
+}
+
+impl MarkupPolicy {
+    /// Allow `tags`, each with a list of attributes:
+    ///
+    /// ```rust
+    /// # use handlebars_fluent::MarkupPolicy;
+    /// let policy = MarkupPolicy::allow(&[("strong", &[]), ("em", &[]), ("br", &[]), ("a", &["href"])]);
+    /// ```
+    pub fn allow(tags: &[(&str, &[&str])]) -> Self {
+        let tags = tags
+            .iter()
+            .map(|(tag, attributes)| {
+                let attributes = attributes.iter().map(|a| a.to_ascii_lowercase()).collect();
+                (tag.to_ascii_lowercase(), attributes)
+            })
+            .collect();
+        MarkupPolicy::AllowList(tags)
+    }
+
+    /// Apply the policy to a formatted message, whose arguments have already been escaped
+    pub(crate) fn apply(&self, text: &str) -> String {
+        match self {
+            MarkupPolicy::Escape => sanitize(text, None),
+            MarkupPolicy::AllowList(tags) => sanitize(text, Some(tags)),
+            MarkupPolicy::AllowAll => text.to_string(),
+        }
+    }
+}
+
+/// Escape `text`, except for entity references and the tags in `allowed`
+fn sanitize(text: &str, allowed: Option<&HashMap<String, HashSet<String>>>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '<' => match allowed.and_then(|allowed| allowed_tag(rest, allowed)) {
+                Some((tag, len)) => {
+                    out.push_str(&tag);
+                    len
+                }
+                None => {
+                    out.push_str("&lt;");
+                    1
+                }
+            },
+            '&' => match entity_len(rest) {
+                Some(len) => {
+                    out.push_str(&rest[..len]);
+                    len
+                }
+                None => {
+                    out.push_str("&amp;");
+                    1
+                }
+            },
+            '>' => {
+                out.push_str("&gt;");
+                1
+            }
+            c => {
+                out.push(c);
+                c.len_utf8()
+            }
+        };
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// The length of the entity reference at the start of `s`, if there is one
+fn entity_len(s: &str) -> Option<usize> {
+    let body = s.strip_prefix('&')?;
+    let end = body.find(';')?;
+    let name = &body[..end];
+    let valid = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit())
+    } else {
+        name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+    if valid {
+        Some(end + 2)
+    } else {
+        None
+    }
+}
+
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(String, Option<&'a str>)>,
+}
+
+/// If `s` starts with an allowed tag, return it rewritten with only the allowed attributes,
+/// and the length of the original
+fn allowed_tag(s: &str, allowed: &HashMap<String, HashSet<String>>) -> Option<(String, usize)> {
+    let (tag, len) = parse_tag(s)?;
+    let attributes = allowed.get(&tag.name)?;
+    if tag.closing {
+        return Some((format!("</{}>", tag.name), len));
+    }
+    let mut out = format!("<{}", tag.name);
+    for (name, value) in tag.attributes {
+        if !attributes.contains(&name) {
+            continue;
+        }
+        match value {
+            Some(value) if URL_ATTRIBUTES.contains(&&*name) && !is_safe_url(value) => {}
+            Some(value) => {
+                let value = sanitize(value, None).replace('"', "&quot;");
+                out.push_str(&format!(" {}=\"{}\"", name, value));
+            }
+            None => out.push_str(&format!(" {}", name)),
+        }
+    }
+    out.push_str(if tag.self_closing { "/>" } else { ">" });
+    Some((out, len))
+}
+
+/// Whether a URL is relative or uses one of the [`URL_SCHEMES`], once its character
+/// references are decoded the way a browser would
+fn is_safe_url(url: &str) -> bool {
+    let url: String = decode_entities(url)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => URL_SCHEMES
+            .iter()
+            .any(|scheme| url[..i].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+/// Decode the character references in an attribute value. Named references are only decoded
+/// if they stand for characters that matter to the scheme check.
+fn decode_entities(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded =
+            entity_len(rest).and_then(|len| Some((decode_entity(&rest[1..len - 1])?, len)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The character an entity reference, given without `&` and `;`, stands for
+fn decode_entity(name: &str) -> Option<char> {
+    let code = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        decimal.parse().ok()?
+    } else {
+        return match name {
+            "colon" => Some(':'),
+            "sol" => Some('/'),
+            "quest" => Some('?'),
+            "num" => Some('#'),
+            "Tab" => Some('\t'),
+            "NewLine" => Some('\n'),
+            "amp" => Some('&'),
+            _ => None,
+        };
+    };
+    // Browsers decode invalid code points as the replacement character
+    Some(char::from_u32(code).unwrap_or('\u{fffd}'))
+}
+
+/// Parse the tag at the start of `s`, returning it and its length
+fn parse_tag(s: &str) -> Option<(Tag<'_>, usize)> {
+    let mut pos = 1;
+    let closing = s[pos..].starts_with('/');
+    if closing {
+        pos += 1;
+    }
+    let name_len = s[pos..]
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(s.len() - pos);
+    let name = &s[pos..pos + name_len];
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    pos += name_len;
+
+    let mut attributes = Vec::new();
+    loop {
+        pos += s[pos..].len() - s[pos..].trim_start().len();
+        let rest = &s[pos..];
+        if rest.starts_with('>') {
+            pos += 1;
+            break;
+        }
+        if rest.starts_with("/>") && !closing {
+            let tag = Tag {
+                name: name.to_ascii_lowercase(),
+                closing,
+                self_closing: true,
+                attributes,
+            };
+            return Some((tag, pos + 2));
+        }
+        if closing {
+            return None;
+        }
+        let attr_len = rest
+            .find(|c: char| c.is_whitespace() || "\"'<>/=".contains(c))
+            .unwrap_or(rest.len());
+        if attr_len == 0 {
+            return None;
+        }
+        let attr = rest[..attr_len].to_ascii_lowercase();
+        pos += attr_len;
+        let after = s[pos..].trim_start();
+        let value = if let Some(after) = after.strip_prefix('=') {
+            let after = after.trim_start();
+            pos = s.len() - after.len();
+            let (value, len) = match after.chars().next()? {
+                quote @ ('"' | '\'') => {
+                    let end = after[1..].find(quote)?;
+                    (&after[1..end + 1], end + 2)
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    (&after[..end], end)
+                }
+            };
+            pos += len;
+            Some(value)
+        } else {
+            None
+        };
+        attributes.push((attr, value));
+    }
+    let tag = Tag {
+        name: name.to_ascii_lowercase(),
+        closing,
+        self_closing: false,
+        attributes,
+    };
+    Some((tag, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        let policy = MarkupPolicy::Escape;
+        assert_eq!(
+            policy.apply("<b>Tom &amp; Jerry</b> & co &nbsp;&#169;&#xA9;"),
+            "&lt;b&gt;Tom &amp; Jerry&lt;/b&gt; &amp; co &nbsp;&#169;&#xA9;"
+        );
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let policy = MarkupPolicy::allow(&[("strong", &[]), ("br", &[]), ("a", &["href"])]);
+        assert_eq!(
+            policy.apply("<STRONG class=x>hi</strong><br/><em>no</em> 1 < 2"),
+            "<strong>hi</strong><br/>&lt;em&gt;no&lt;/em&gt; 1 &lt; 2"
+        );
+        assert_eq!(
+            policy.apply(r#"<a href="/docs?a=1&b" onclick="x()">docs</a>"#),
+            r#"<a href="/docs?a=1&amp;b">docs</a>"#
+        );
+        assert_eq!(
+            policy.apply(r#"<a href=' java script:alert(1)'>x</a> <a href=mailto:a@b.c>y</a>"#),
+            r#"<a>x</a> <a href="mailto:a@b.c">y</a>"#
+        );
+        assert_eq!(
+            policy.apply(
+                r#"<a href="javascript&colon;alert(1)">x</a><a href="javascript&#58;alert(2)">y</a>"#
+            ),
+            "<a>x</a><a>y</a>"
+        );
+        assert_eq!(
+            policy.apply(
+                r#"<a href="java&#x09;script&#x3A;x">x</a><a href="&#104;ttps://a.b/">y</a>"#
+            ),
+            r#"<a>x</a><a href="&#104;ttps://a.b/">y</a>"#
+        );
+        assert_eq!(policy.apply("<a href=\"x"), "&lt;a href=\"x");
+    }
+
+    #[test]
+    fn test_allow_all() {
+        let policy = MarkupPolicy::AllowAll;
+        assert_eq!(policy.apply("<script>x</script>"), "<script>x</script>");
+    }
+}
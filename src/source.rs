@@ -0,0 +1,506 @@
+//! Where the FTL files and metadata of locales are loaded from.
+//!
+//! [`build_resources()`](crate::loader::build_resources) and
+//! [`build_metadata()`](crate::loader::build_metadata), and so [`simple_loader!()`](crate::simple_loader),
+//! accept any [`ResourceSource`]:
+//!
+//! - a folder on disk, given as a `&str` or [`Path`], containing one folder per locale
+//! - files [embedded](crate::build) in the binary, as a list of
+//!   [`EmbeddedLocale`]s
+//! - a [`MemorySource`], e.g. for tests
+#![cfg_attr(
+    feature = "zip",
+    doc = "- a [`ZipSource`] reading a translation pack, with the `zip` feature"
+)]
+#![cfg_attr(
+    not(feature = "zip"),
+    doc = "- a `ZipSource` reading a translation pack, with the `zip` feature"
+)]
+//!
+//! The FTL file shared by all locales, given with `core:`, can be any [`CoreSource`]: a path,
+//! or [`FtlText`].
+//!
+//! Locale names are matched as identifiers, so a folder named `en_us` holds the strings for
+//! `en-US`.
+
+use std::collections::BTreeMap;
+use std::fs::{self, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::loader::{EmbeddedLocale, LanguageIdentifier};
+
+/// A collection of locales, each with named FTL sources and optional metadata
+pub trait ResourceSource {
+    /// The locales in this source
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>>;
+
+    /// The FTL sources of `lang`, as pairs of a name (such as a file name) and the FTL text
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>>;
+
+    /// The contents of the `meta.toml` of `lang`, if it has one. See
+    /// [`LocaleMetadata`](crate::LocaleMetadata).
+    fn metadata(&self, _lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+impl<S: ResourceSource + ?Sized> ResourceSource for &S {
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+        (**self).locales()
+    }
+
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+        (**self).resources(lang)
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        (**self).metadata(lang)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_lang(lang: &str) -> io::Result<LanguageIdentifier> {
+    lang.parse()
+        .map_err(|e| invalid_data(format!("Invalid locale {}: {}", lang, e)))
+}
+
+/// The FTL files in `dir`, sorted by name
+pub(crate) fn ftl_files(dir: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+
+        // Prevent loading non-FTL files as translations, such as VIM temporary files.
+        if entry.path().extension().and_then(|e| e.to_str()) != Some("ftl") {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        files.push((name, fs::read_to_string(entry.path())?));
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The folder of `lang` in `dir`, whose name may be written differently, like `zh_CN`
+fn locale_dir(dir: &Path, lang: &LanguageIdentifier) -> io::Result<Option<PathBuf>> {
+    let canonical = dir.join(lang.to_string());
+    if canonical.is_dir() {
+        return Ok(Some(canonical));
+    }
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        if name.to_str().and_then(|name| name.parse().ok()).as_ref() == Some(lang) {
+            return Ok(Some(entry.path()));
+        }
+    }
+    Ok(None)
+}
+
+/// A folder with one folder per locale code, each containing FTL files and optionally a
+/// `meta.toml`
+impl ResourceSource for Path {
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+        let mut locales = Vec::new();
+        for entry in read_dir(self)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Ok(lang) = entry.file_name().into_string() {
+                    locales.push(parse_lang(&lang)?);
+                }
+            }
+        }
+        Ok(locales)
+    }
+
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+        match locale_dir(self, lang)? {
+            Some(dir) => ftl_files(&dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        let Some(dir) = locale_dir(self, lang)? else {
+            return Ok(None);
+        };
+        let path = dir.join("meta.toml");
+        if path.is_file() {
+            fs::read_to_string(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! forward_to_path {
+    ($($ty:ty),*) => {
+        $(
+            impl ResourceSource for $ty {
+                fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+                    ResourceSource::locales(Path::new(self))
+                }
+
+                fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+                    ResourceSource::resources(Path::new(self), lang)
+                }
+
+                fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+                    ResourceSource::metadata(Path::new(self), lang)
+                }
+            }
+        )*
+    };
+}
+
+forward_to_path!(str, String, PathBuf);
+
+/// Where the FTL shared by all locales, given with `core:`, is read from: a file path, or
+/// [`FtlText`] for text that is already loaded, e.g. with `include_str!()`
+pub trait CoreSource {
+    /// The FTL text
+    fn core(&self) -> io::Result<String>;
+}
+
+impl<S: CoreSource + ?Sized> CoreSource for &S {
+    fn core(&self) -> io::Result<String> {
+        (**self).core()
+    }
+}
+
+impl CoreSource for Path {
+    fn core(&self) -> io::Result<String> {
+        fs::read_to_string(self)
+    }
+}
+
+impl CoreSource for str {
+    fn core(&self) -> io::Result<String> {
+        Path::new(self).core()
+    }
+}
+
+impl CoreSource for String {
+    fn core(&self) -> io::Result<String> {
+        Path::new(self).core()
+    }
+}
+
+impl CoreSource for PathBuf {
+    fn core(&self) -> io::Result<String> {
+        self.as_path().core()
+    }
+}
+
+/// FTL text to use as it is, rather than a path to read it from:
+///
+/// ```rust
+/// use handlebars_fluent::*;
+///
+/// simple_loader!(create_loader, "./tests/locales/", "en-US",
+///                core: source::FtlText(include_str!("../tests/locales/core.ftl")),
+///                customizer: |_bundle, _lang| {});
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FtlText<T>(pub T);
+
+impl<T: AsRef<str>> CoreSource for FtlText<T> {
+    fn core(&self) -> io::Result<String> {
+        Ok(self.0.as_ref().to_string())
+    }
+}
+
+impl EmbeddedLocale {
+    fn find<'a>(locales: &'a [Self], lang: &LanguageIdentifier) -> Option<&'a Self> {
+        locales
+            .iter()
+            .find(|locale| parse_lang(locale.lang).ok().as_ref() == Some(lang))
+    }
+}
+
+/// Files compiled into the binary, see [`build::embed_locales()`](crate::build::embed_locales)
+impl ResourceSource for [EmbeddedLocale] {
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+        self.iter().map(|locale| parse_lang(locale.lang)).collect()
+    }
+
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+        let resources = EmbeddedLocale::find(self, lang).map_or(&[][..], |l| l.resources);
+        Ok(resources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| (format!("{}/{}", lang, i), source.to_string()))
+            .collect())
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        Ok(EmbeddedLocale::find(self, lang)
+            .and_then(|l| l.metadata)
+            .map(String::from))
+    }
+}
+
+impl<const N: usize> ResourceSource for [EmbeddedLocale; N] {
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+        self[..].locales()
+    }
+
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+        self[..].resources(lang)
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        self[..].metadata(lang)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct MemoryLocale {
+    resources: Vec<(String, String)>,
+    metadata: Option<String>,
+}
+
+/// Locales held in memory:
+///
+/// ```rust
+/// use handlebars_fluent::source::MemorySource;
+///
+/// let source = MemorySource::new()
+///     .with_resource("en-US", "main.ftl", "hello = Hello")
+///     .with_resource("fr", "main.ftl", "hello = Bonjour")
+///     .with_metadata("fr", r#"native-name = "Français""#);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MemorySource {
+    locales: BTreeMap<String, MemoryLocale>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an FTL source named `name` to `lang`
+    pub fn with_resource(
+        mut self,
+        lang: impl Into<String>,
+        name: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Self {
+        self.add_resource(lang, name, source);
+        self
+    }
+
+    /// Set the contents of the `meta.toml` of `lang`
+    pub fn with_metadata(mut self, lang: impl Into<String>, metadata: impl Into<String>) -> Self {
+        self.add_metadata(lang, metadata);
+        self
+    }
+
+    /// Add an FTL source named `name` to `lang`
+    pub fn add_resource(
+        &mut self,
+        lang: impl Into<String>,
+        name: impl Into<String>,
+        source: impl Into<String>,
+    ) {
+        let locale = self.locales.entry(lang.into()).or_default();
+        locale.resources.push((name.into(), source.into()));
+    }
+
+    /// Set the contents of the `meta.toml` of `lang`
+    pub fn add_metadata(&mut self, lang: impl Into<String>, metadata: impl Into<String>) {
+        self.locales.entry(lang.into()).or_default().metadata = Some(metadata.into());
+    }
+
+    fn get(&self, lang: &LanguageIdentifier) -> Option<&MemoryLocale> {
+        self.locales
+            .iter()
+            .find(|(name, _)| parse_lang(name).ok().as_ref() == Some(lang))
+            .map(|(_, locale)| locale)
+    }
+}
+
+impl ResourceSource for MemorySource {
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+        self.locales.keys().map(|lang| parse_lang(lang)).collect()
+    }
+
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+        Ok(self
+            .get(lang)
+            .map(|l| l.resources.clone())
+            .unwrap_or_default())
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        Ok(self.get(lang).and_then(|l| l.metadata.clone()))
+    }
+}
+
+/// A zip archive with the same layout as a locales folder, such as a translation pack
+/// downloaded at runtime. Enabled with the `zip` feature.
+///
+/// Entries are read into memory when the archive is opened. Only `<locale>/<name>.ftl` and
+/// `<locale>/meta.toml` entries are used.
+///
+/// ```rust,no_run
+/// # fn open() -> std::io::Result<()> {
+/// use handlebars_fluent::source::ZipSource;
+///
+/// let source = ZipSource::new(std::fs::File::open("translations.zip")?)?;
+/// let resources = handlebars_fluent::loader::build_resources(&source);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "zip")]
+#[derive(Clone, Debug)]
+pub struct ZipSource {
+    memory: MemorySource,
+}
+
+#[cfg(feature = "zip")]
+impl ZipSource {
+    pub fn new(reader: impl io::Read + io::Seek) -> io::Result<Self> {
+        use std::io::Read;
+
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut memory = MemorySource::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if !file.is_file() {
+                continue;
+            }
+            let Some(path) = file.enclosed_name() else {
+                continue;
+            };
+            let mut components = path.iter().map(|c| c.to_string_lossy().into_owned());
+            let (Some(lang), Some(name), None) =
+                (components.next(), components.next(), components.next())
+            else {
+                continue;
+            };
+            let is_ftl = Path::new(&name).extension().and_then(|e| e.to_str()) == Some("ftl");
+            if !is_ftl && name != "meta.toml" {
+                continue;
+            }
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)?;
+            if is_ftl {
+                memory.add_resource(lang, name, contents);
+            } else {
+                memory.add_metadata(lang, contents);
+            }
+        }
+        for locale in memory.locales.values_mut() {
+            locale.resources.sort();
+        }
+        Ok(Self { memory })
+    }
+}
+
+#[cfg(feature = "zip")]
+impl ResourceSource for ZipSource {
+    fn locales(&self) -> io::Result<Vec<LanguageIdentifier>> {
+        self.memory.locales()
+    }
+
+    fn resources(&self, lang: &LanguageIdentifier) -> io::Result<Vec<(String, String)>> {
+        self.memory.resources(lang)
+    }
+
+    fn metadata(&self, lang: &LanguageIdentifier) -> io::Result<Option<String>> {
+        self.memory.metadata(lang)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_source() {
+        let dir = Path::new("./tests/locales");
+        let mut locales = dir.locales().unwrap();
+        locales.sort_by_key(|l| l.to_string());
+        let locales: Vec<_> = locales.iter().map(|l| l.to_string()).collect();
+        assert_eq!(locales, ["en-US", "fr", "he", "zh-CN", "zh-TW"]);
+
+        let fr = "fr".parse().unwrap();
+        let resources = "./tests/locales".resources(&fr).unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].0, "main.ftl");
+        assert!(ResourceSource::metadata(dir, &fr).unwrap().is_some());
+        let en = "en-US".parse().unwrap();
+        assert!(ResourceSource::metadata(dir, &en).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_locale_names() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("en-us"))?;
+        fs::write(dir.path().join("en-us/main.ftl"), "hello = Hello\n")?;
+        fs::create_dir(dir.path().join("zh_CN"))?;
+        fs::write(dir.path().join("zh_CN/main.ftl"), "hello = 你好\n")?;
+        fs::write(
+            dir.path().join("zh_CN/meta.toml"),
+            "native-name = \"中文\"\n",
+        )?;
+
+        let en: LanguageIdentifier = "en-US".parse()?;
+        let zh: LanguageIdentifier = "zh-CN".parse()?;
+        assert_eq!(dir.path().resources(&en)?.len(), 1);
+        assert_eq!(dir.path().resources(&zh)?.len(), 1);
+        assert!(ResourceSource::metadata(dir.path(), &zh)?.is_some());
+
+        let embedded = [EmbeddedLocale {
+            lang: "zh_CN",
+            resources: &["hello = 你好\n"],
+            metadata: None,
+        }];
+        assert_eq!(embedded.resources(&zh)?.len(), 1);
+
+        let memory = MemorySource::new().with_resource("en-us", "main.ftl", "hello = Hello");
+        assert_eq!(memory.resources(&en)?.len(), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_zip_source() -> Result<(), Box<dyn std::error::Error>> {
+        use std::io::Write;
+
+        let mut archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        archive.start_file("fr/main.ftl", options)?;
+        archive.write_all(b"hello = Bonjour\n")?;
+        archive.start_file("fr/meta.toml", options)?;
+        archive.write_all(b"native-name = \"Fran\xc3\xa7ais\"\n")?;
+        archive.start_file("fr/notes.txt", options)?;
+        archive.write_all(b"not a resource")?;
+        archive.start_file("README.md", options)?;
+        archive.write_all(b"# Translations")?;
+        let archive = archive.finish()?;
+
+        let source = ZipSource::new(archive)?;
+        let fr: LanguageIdentifier = "fr".parse()?;
+        assert_eq!(source.locales()?, vec![fr.clone()]);
+        assert_eq!(
+            source.resources(&fr)?,
+            vec![("main.ftl".to_string(), "hello = Bonjour\n".to_string())]
+        );
+        assert_eq!(
+            source.metadata(&fr)?.as_deref(),
+            Some("native-name = \"Français\"\n")
+        );
+        Ok(())
+    }
+}
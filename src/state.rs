@@ -0,0 +1,51 @@
+//! Render state shared between helpers, such as a locale override.
+//!
+//! Block locals don't survive partials, but local helpers do, so each value is stored as a
+//! local helper under a name that can't be called from a template.
+
+use handlebars::{Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, ScopedJson};
+use serde_json::Value as Json;
+
+/// The locale set by `{{#fluent-locale}}`
+pub(crate) const LOCALE: &str = "@fluent-locale";
+
+/// The prefix of relative message ids, set by `{{#fluent-scope}}`
+pub(crate) const SCOPE: &str = "@fluent-scope";
+
+/// The locale of the whole render, set by `{{*fluent-lang}}`
+pub(crate) const LANG: &str = "@fluent-lang";
+
+struct Value(String);
+
+impl HelperDef for Value {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        _: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        Ok(ScopedJson::Derived(Json::String(self.0.clone())))
+    }
+}
+
+pub(crate) fn get<'reg: 'rc, 'rc>(
+    name: &str,
+    h: &Helper<'rc>,
+    reg: &'reg Handlebars<'reg>,
+    context: &'rc Context,
+    rcx: &mut RenderContext<'reg, 'rc>,
+) -> Result<Option<String>, RenderError> {
+    let Some(value) = rcx.get_local_helper(name) else {
+        return Ok(None);
+    };
+    let value = value.call_inner(h, reg, context, rcx)?;
+    Ok(value.as_json().as_str().map(String::from))
+}
+
+pub(crate) fn set(name: &str, value: Option<String>, rcx: &mut RenderContext) {
+    match value {
+        Some(value) => rcx.register_local_helper(name, Box::new(Value(value))),
+        None => rcx.unregister_local_helper(name),
+    }
+}
@@ -0,0 +1,436 @@
+//! Custom [`FluentType`]s for values that Fluent has no native representation for.
+//!
+//! With the `icu` feature enabled these are formatted according to the locale of the
+//! bundle they are rendered in, otherwise a locale-independent fallback is used.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use fluent_bundle::types::FluentType;
+use fluent_bundle::FluentValue;
+
+/// The length of a formatted date or time, as in `Intl.DateTimeFormat`'s
+/// `dateStyle` and `timeStyle` options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DateTimeStyle {
+    Full,
+    Long,
+    Medium,
+    Short,
+}
+
+impl DateTimeStyle {
+    /// Parse a style from its `Intl` name (`"full"`, `"long"`, `"medium"` or `"short"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "full" => Some(Self::Full),
+            "long" => Some(Self::Long),
+            "medium" => Some(Self::Medium),
+            "short" => Some(Self::Short),
+            _ => None,
+        }
+    }
+}
+
+/// Formatting options for a [`FluentDateTime`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FluentDateTimeOptions {
+    pub date_style: Option<DateTimeStyle>,
+    pub time_style: Option<DateTimeStyle>,
+}
+
+impl Default for FluentDateTimeOptions {
+    fn default() -> Self {
+        Self {
+            date_style: Some(DateTimeStyle::Medium),
+            time_style: None,
+        }
+    }
+}
+
+impl FluentDateTimeOptions {
+    /// Merge `dateStyle` and `timeStyle` named arguments into these options.
+    ///
+    /// Specifying only one of the two styles omits the other part from the output.
+    pub fn merge(&mut self, opts: &fluent_bundle::FluentArgs) {
+        let date_style = opts.get("dateStyle").and_then(style_from_value);
+        let time_style = opts.get("timeStyle").and_then(style_from_value);
+        if date_style.is_some() || time_style.is_some() {
+            self.date_style = date_style;
+            self.time_style = time_style;
+        }
+    }
+}
+
+fn style_from_value(value: &FluentValue) -> Option<DateTimeStyle> {
+    match value {
+        FluentValue::String(s) => DateTimeStyle::from_name(s),
+        _ => None,
+    }
+}
+
+/// A point in time, displayed at a fixed offset from UTC
+#[derive(Clone, Debug, PartialEq)]
+pub struct FluentDateTime {
+    timestamp: i64,
+    offset: i32,
+    pub options: FluentDateTimeOptions,
+}
+
+impl FluentDateTime {
+    /// Construct a datetime from seconds since the Unix epoch, displayed at `offset`
+    /// minutes east of UTC.
+    pub fn new(timestamp: i64, offset: i32) -> Self {
+        Self {
+            timestamp,
+            offset,
+            options: Default::default(),
+        }
+    }
+
+    /// Parse an RFC 3339 timestamp such as `2024-05-01T14:30:00+02:00`.
+    ///
+    /// The time (and offset) may be omitted, in which case midnight UTC is used.
+    /// The datetime is displayed at the offset it was written with.
+    pub fn parse_rfc3339(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.len() < 10 || !s.is_char_boundary(10) {
+            return None;
+        }
+        let (date, rest) = s.split_at(10);
+        let mut date = date.splitn(3, '-');
+        let year: i64 = parse_digits(date.next()?, 4)?;
+        let month: i64 = parse_digits(date.next()?, 2)?;
+        let day: i64 = parse_digits(date.next()?, 2)?;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        let (mut hour, mut minute, mut second, mut offset) = (0, 0, 0, 0);
+        if !rest.is_empty() {
+            let rest = rest.strip_prefix(['T', 't', ' '])?;
+            let zone_start = rest.find(['Z', 'z', '+', '-']).unwrap_or(rest.len());
+            let (time, zone) = rest.split_at(zone_start);
+            // Fractional seconds don't affect any of the supported styles
+            let time = time.split('.').next()?;
+            let mut time = time.split(':');
+            hour = parse_digits(time.next()?, 2)?;
+            minute = parse_digits(time.next()?, 2)?;
+            second = time.next().map_or(Some(0), |s| parse_digits(s, 2))?;
+            if time.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+                return None;
+            }
+            offset = parse_offset(zone)?;
+        }
+
+        let local = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+        Some(Self::new(local - i64::from(offset) * 60, offset))
+    }
+
+    /// Display this datetime at `offset` minutes east of UTC instead
+    pub fn with_offset(self, offset: i32) -> Self {
+        Self { offset, ..self }
+    }
+
+    /// Seconds since the Unix epoch
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// The offset from UTC this datetime is displayed at, in minutes
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    /// The local calendar date and wall clock time, as
+    /// `(year, month, day, hour, minute, second)`
+    pub fn civil(&self) -> (i64, u8, u8, u8, u8, u8) {
+        let local = self.timestamp + i64::from(self.offset) * 60;
+        let (days, secs) = (local.div_euclid(86400), local.rem_euclid(86400));
+        let (year, month, day) = civil_from_days(days);
+        (
+            year,
+            month as u8,
+            day as u8,
+            (secs / 3600) as u8,
+            (secs % 3600 / 60) as u8,
+            (secs % 60) as u8,
+        )
+    }
+
+    /// Format as an RFC 3339 timestamp at the display offset
+    pub fn to_rfc3339(&self) -> String {
+        let (year, month, day, hour, minute, second) = self.civil();
+        let mut s = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        );
+        if self.offset == 0 {
+            s.push('Z');
+        } else {
+            let sign = if self.offset < 0 { '-' } else { '+' };
+            let offset = self.offset.abs();
+            let _ = write!(s, "{}{:02}:{:02}", sign, offset / 60, offset % 60);
+        }
+        s
+    }
+}
+
+impl FluentType for FluentDateTime {
+    fn duplicate(&self) -> Box<dyn FluentType + Send> {
+        Box::new(self.clone())
+    }
+
+    // Only concurrent bundles are created by this crate, so this doesn't bother with
+    // locale-aware formatting.
+    fn as_string(&self, _intls: &intl_memoizer::IntlLangMemoizer) -> Cow<'static, str> {
+        self.to_rfc3339().into()
+    }
+
+    fn as_string_threadsafe(
+        &self,
+        intls: &intl_memoizer::concurrent::IntlLangMemoizer,
+    ) -> Cow<'static, str> {
+        #[cfg(feature = "icu")]
+        if let Ok(Some(s)) = intls
+            .with_try_get::<crate::intl::DateTimeFormat, _, _>(self.options, |dtf| dtf.format(self))
+        {
+            return s.into();
+        }
+        #[cfg(not(feature = "icu"))]
+        let _ = intls;
+        self.to_rfc3339().into()
+    }
+}
+
+/// The kind of list produced by a [`FluentList`], as in `Intl.ListFormat`'s `type` option
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ListType {
+    /// "A, B, and C"
+    #[default]
+    Conjunction,
+    /// "A, B, or C"
+    Disjunction,
+    /// "A, B, C"
+    Unit,
+}
+
+/// The width of a [`FluentList`], as in `Intl.ListFormat`'s `style` option
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ListStyle {
+    #[default]
+    Long,
+    Short,
+    Narrow,
+}
+
+/// Formatting options for a [`FluentList`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FluentListOptions {
+    pub r#type: ListType,
+    pub style: ListStyle,
+}
+
+impl FluentListOptions {
+    /// Merge `type` and `style` named arguments into these options
+    pub fn merge(&mut self, opts: &fluent_bundle::FluentArgs) {
+        for (key, value) in opts.iter() {
+            match (key, value) {
+                ("type", FluentValue::String(s)) => match &**s {
+                    "conjunction" => self.r#type = ListType::Conjunction,
+                    "disjunction" => self.r#type = ListType::Disjunction,
+                    "unit" => self.r#type = ListType::Unit,
+                    _ => {}
+                },
+                ("style", FluentValue::String(s)) => match &**s {
+                    "long" => self.style = ListStyle::Long,
+                    "short" => self.style = ListStyle::Short,
+                    "narrow" => self.style = ListStyle::Narrow,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A list of values that is joined into a single string when formatted
+#[derive(Clone, Debug, PartialEq)]
+pub struct FluentList {
+    items: Vec<FluentValue<'static>>,
+    pub options: FluentListOptions,
+}
+
+impl FluentList {
+    pub fn new(items: Vec<FluentValue<'static>>) -> Self {
+        Self {
+            items,
+            options: Default::default(),
+        }
+    }
+
+    pub fn items(&self) -> &[FluentValue<'static>] {
+        &self.items
+    }
+
+    fn join(items: &[String]) -> String {
+        items.join(", ")
+    }
+}
+
+impl FluentType for FluentList {
+    fn duplicate(&self) -> Box<dyn FluentType + Send> {
+        Box::new(self.clone())
+    }
+
+    fn as_string(&self, intls: &intl_memoizer::IntlLangMemoizer) -> Cow<'static, str> {
+        let items: Vec<String> = self
+            .items
+            .iter()
+            .map(|item| match item {
+                FluentValue::String(s) => s.to_string(),
+                FluentValue::Number(n) => n.as_string().into(),
+                FluentValue::Custom(c) => c.as_string(intls).into(),
+                _ => String::new(),
+            })
+            .collect();
+        Self::join(&items).into()
+    }
+
+    fn as_string_threadsafe(
+        &self,
+        intls: &intl_memoizer::concurrent::IntlLangMemoizer,
+    ) -> Cow<'static, str> {
+        // Items need to be formatted before the list formatter is borrowed, since the
+        // memoizer can't be used reentrantly.
+        let items: Vec<String> = self
+            .items
+            .iter()
+            .map(|item| match item {
+                FluentValue::String(s) => s.to_string(),
+                #[cfg(feature = "icu")]
+                FluentValue::Number(n) => crate::intl::format_number(n, intls),
+                #[cfg(not(feature = "icu"))]
+                FluentValue::Number(n) => n.as_string().into(),
+                FluentValue::Custom(c) => c.as_string_threadsafe(intls).into(),
+                _ => String::new(),
+            })
+            .collect();
+        #[cfg(feature = "icu")]
+        if let Ok(s) = intls
+            .with_try_get::<crate::intl::ListFormat, _, _>(self.options, |lf| lf.format(&items))
+        {
+            return s.into();
+        }
+        Self::join(&items).into()
+    }
+}
+
+fn parse_digits(s: &str, len: usize) -> Option<i64> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Parse an RFC 3339 offset (`Z`, `UTC`, `+02:00`, `-0530`), returning minutes east of UTC.
+/// An empty string is treated as UTC.
+pub fn parse_offset(zone: &str) -> Option<i32> {
+    match zone {
+        "" | "Z" | "z" | "UTC" => return Some(0),
+        _ => {}
+    }
+    // Offsets are ASCII, and slicing others could split a character
+    if !zone.is_ascii() {
+        return None;
+    }
+    let (sign, zone) = match zone.as_bytes()[0] {
+        b'+' => (1, &zone[1..]),
+        b'-' => (-1, &zone[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match zone.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if zone.len() == 4 => zone.split_at(2),
+        None => (zone, "00"),
+    };
+    let hours = parse_digits(hours, 2)?;
+    let minutes = parse_digits(minutes, 2)?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes) as i32)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversions between days since the Unix epoch and proleptic Gregorian dates,
+// from http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let dt = FluentDateTime::parse_rfc3339("2024-02-29T14:30:05+02:00").unwrap();
+        assert_eq!(dt.timestamp(), 1709209805);
+        assert_eq!(dt.offset(), 120);
+        assert_eq!(dt.civil(), (2024, 2, 29, 14, 30, 5));
+        assert_eq!(dt.to_rfc3339(), "2024-02-29T14:30:05+02:00");
+
+        let dt = FluentDateTime::parse_rfc3339("1969-12-31").unwrap();
+        assert_eq!(dt.timestamp(), -86400);
+        assert_eq!(dt.to_rfc3339(), "1969-12-31T00:00:00Z");
+
+        let dt = FluentDateTime::parse_rfc3339("2000-01-01T00:00:00.250Z").unwrap();
+        assert_eq!(dt.timestamp(), 946684800);
+
+        assert_eq!(FluentDateTime::parse_rfc3339("2023-02-29"), None);
+        assert_eq!(FluentDateTime::parse_rfc3339("2023-01-01T25:00:00Z"), None);
+        assert_eq!(FluentDateTime::parse_rfc3339("yesterday"), None);
+        assert_eq!(FluentDateTime::parse_rfc3339("2024-01-01T10:00+aé1"), None);
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("Z"), Some(0));
+        assert_eq!(parse_offset("+02:00"), Some(120));
+        assert_eq!(parse_offset("-0530"), Some(-330));
+        assert_eq!(parse_offset("+05"), Some(300));
+        assert_eq!(parse_offset("+24:00"), None);
+        assert_eq!(parse_offset("+aé1"), None);
+    }
+}
@@ -0,0 +1,75 @@
+use handlebars::*;
+use handlebars_fluent::*;
+use serde_json::json;
+
+fn upper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let text = h.param(0).and_then(|p| p.value().as_str()).unwrap_or("");
+    out.write(&text.to_uppercase())?;
+    Ok(())
+}
+
+fn truncate(
+    h: &Helper,
+    _: &Handlebars,
+    context: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let text = h.param(0).and_then(|p| p.value().as_str()).unwrap_or("");
+    let length = h
+        .hash_get("length")
+        .and_then(|l| l.value().as_u64())
+        .unwrap_or(10) as usize;
+    let lang = context.data()["lang"].as_str().unwrap_or("");
+    out.write(&format!(
+        "{}… ({})",
+        text.chars().take(length).collect::<String>(),
+        lang
+    ))?;
+    Ok(())
+}
+
+lazy_static::lazy_static! {
+    static ref HELPERS: Handlebars<'static> = {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("upper", Box::new(upper));
+        handlebars.register_helper("truncate", Box::new(truncate));
+        handlebars
+    };
+}
+
+simple_loader!(load, "./tests/locales", "en-US", core: "./tests/locales/core.ftl", customizer: |bundle, lang| {
+    bridge::add_helper_functions(bundle, lang, &HELPERS, &[("UPPER", "upper"), ("TRUNCATE", "truncate")]).unwrap();
+});
+
+#[test]
+fn test_helper_functions() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "en-US", "name": "Ann", "title": "A long title"});
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "shout" name=name}}"#, &data)
+            .unwrap(),
+        "ANN!"
+    );
+    // The second call reuses the compiled template with the new arguments
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "shout" name="Bob"}}"#, &data)
+            .unwrap(),
+        "BOB!"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "truncated-title" title=title}}"#, &data)
+            .unwrap(),
+        "A lon… (en-US)"
+    );
+}
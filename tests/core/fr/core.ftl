@@ -0,0 +1 @@
+-brand = Fluent pour Handlebars
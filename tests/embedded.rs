@@ -0,0 +1,42 @@
+use handlebars::*;
+use handlebars_fluent::loader::EmbeddedLocale;
+use handlebars_fluent::*;
+use serde_json::json;
+
+// What `build::embed_locales()` generates for a folder with these two locales
+simple_loader!(load, embedded: &[
+    EmbeddedLocale {
+        lang: "en-US",
+        resources: &[include_str!("locales/en-US/main.ftl")],
+        metadata: None,
+    },
+    EmbeddedLocale {
+        lang: "fr",
+        resources: &[include_str!("locales/fr/main.ftl")],
+        metadata: Some(include_str!("locales/fr/meta.toml")),
+    },
+], "en-US");
+
+#[test]
+fn test_embedded() {
+    let loader = load();
+    let locales: Vec<String> = loader.locales().iter().map(|l| l.to_string()).collect();
+    assert_eq!(locales, ["en-US", "fr"]);
+    assert_eq!(
+        loader.native_name(&"fr".parse().unwrap()).as_deref(),
+        Some("Français")
+    );
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(loader)));
+    let render = |lang: &str| {
+        handlebars
+            .render_template(
+                r#"{{fluent "simple"}} | {{fluent "fallback"}}"#,
+                &json!({ "lang": lang }),
+            )
+            .unwrap()
+    };
+    assert_eq!(render("fr"), "texte simple | this should fall back");
+    assert_eq!(render("en-US"), "simple text | this should fall back");
+}
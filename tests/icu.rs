@@ -0,0 +1,137 @@
+#![cfg(feature = "icu")]
+
+use handlebars::*;
+use handlebars_fluent::*;
+
+simple_loader!(load, "./tests/locales", "en-US", core: "./tests/locales/core.ftl", customizer: |bundle, lang| {
+    let lang = lang.to_string();
+    bundle.add_function("LOCALE", move |_, _| lang.clone().into()).unwrap();
+});
+
+use serde_json::json;
+
+fn render(lang: &str, template: &str) -> String {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    handlebars
+        .render_template(template, &json!({ "lang": lang }))
+        .unwrap()
+}
+
+#[test]
+fn test_number() {
+    assert_eq!(
+        render("en-US", r#"{{fluent "count" n=12345}}"#),
+        "12,345 items"
+    );
+    assert_eq!(
+        render("fr", r#"{{fluent "count" n=12345}}"#),
+        "12\u{202f}345 articles"
+    );
+    assert_eq!(
+        render("en-US", r#"{{fluent "price" amount=1234.5}}"#),
+        "costs EUR\u{a0}1,234.50"
+    );
+    assert_eq!(
+        render("fr", r#"{{fluent "price" amount=1234.5}}"#),
+        "coûte EUR\u{a0}1\u{202f}234,50"
+    );
+    assert_eq!(
+        render("en-US", r#"{{fluent "count" n=0.256 n-style="percent"}}"#),
+        "26% items"
+    );
+    assert_eq!(
+        render(
+            "en-US",
+            r#"{{fluent "count" n=1234 n-useGrouping="false"}}"#
+        ),
+        "1234 items"
+    );
+}
+
+#[test]
+fn test_datetime() {
+    let when = r#"when="2024-05-01T14:30:00+02:00""#;
+    assert_eq!(
+        render("en-US", &format!(r#"{{{{fluent "published" {}}}}}"#, when)),
+        "published May 1, 2024"
+    );
+    assert_eq!(
+        render("fr", &format!(r#"{{{{fluent "published" {}}}}}"#, when)),
+        "publié le 1 mai 2024"
+    );
+    assert_eq!(
+        render("en-US", &format!(r#"{{{{fluent "updated" {}}}}}"#, when)),
+        "updated 5/1/24, 2:30\u{202f}PM"
+    );
+    assert_eq!(
+        render(
+            "en-US",
+            &format!(
+                r#"{{{{fluent "placed" {} when-type="datetime" when-dateStyle="short" when-timeStyle="short" when-timeZone="UTC"}}}}"#,
+                when
+            )
+        ),
+        "placed on 5/1/24, 12:30\u{202f}PM"
+    );
+    assert_eq!(
+        render(
+            "fr",
+            &format!(
+                r#"{{{{fluent "published" {} when-type="datetime" when-timeZone="-14:00"}}}}"#,
+                when
+            )
+        ),
+        "publié le 30 avril 2024"
+    );
+}
+
+#[test]
+fn test_list() {
+    let args = r#"a="Ann" b="Bob" c="Cat""#;
+    assert_eq!(
+        render("en-US", &format!(r#"{{{{fluent "authors" {}}}}}"#, args)),
+        "by Ann, Bob, and Cat"
+    );
+    assert_eq!(
+        render("fr", &format!(r#"{{{{fluent "authors" {}}}}}"#, args)),
+        "par Ann, Bob et Cat"
+    );
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let authors = json!([{"name": "Ann"}, {"name": "Bob"}, {"name": "Cat"}]);
+    let cases = [
+        ("en-US", "written-by", "", "written by Ann, Bob, and Cat"),
+        ("fr", "written-by", "", "écrit par Ann, Bob et Cat"),
+        ("zh-CN", "written-by", "", "Ann、Bob和Cat著"),
+        (
+            "en-US",
+            "written-by",
+            r#"authors-type="disjunction""#,
+            "written by Ann, Bob, or Cat",
+        ),
+        ("en-US", "either", "", "either Ann, Bob, or Cat"),
+    ];
+    for (lang, id, options, expected) in cases {
+        let template = format!(
+            r#"{{{{fluent "{}" authors=authors authors-field="name" {}}}}}"#,
+            id, options
+        );
+        let data = json!({ "lang": lang, "authors": authors });
+        assert_eq!(
+            handlebars.render_template(&template, &data).unwrap(),
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_platform() {
+    let expected = if cfg!(target_os = "macos") {
+        "Cmd+S"
+    } else {
+        "Ctrl+S"
+    };
+    assert_eq!(render("en-US", r#"{{fluent "save-shortcut"}}"#), expected);
+}
@@ -1 +1,2 @@
 -something = foo
+-brand = Handlebars Fluent
@@ -6,4 +6,63 @@ parameter2 = text one { $param1 } second { $param2 }
 
 email = text with an EMAIL("example@example.org")
 
-fallback = this should fall back
\ No newline at end of file
+fallback = this should fall back
+
+welcome = welcome to { -brand }
+locale = built for { LOCALE() }
+price = costs { NUMBER($amount, style: "currency", currency: "EUR") }
+count = { $n } items
+published = published { DATETIME($when, dateStyle: "long") }
+updated = updated { DATETIME($when, dateStyle: "short", timeStyle: "short") }
+authors = by { LIST($a, $b, $c) }
+rank = { $n ->
+    [one] {$n}st
+    [two] {$n}nd
+    [few] {$n}rd
+   *[other] {$n}th
+}
+placed = placed on { $when }
+written-by = written by { $authors }
+either = either { LIST($authors, type: "disjunction") }
+save-shortcut = { PLATFORM() ->
+    [macos] Cmd+S
+   *[other] Ctrl+S
+}
+-language-name = English (US)
+language-name-fr = French
+language-name-he = Hebrew
+language-name-zh-CN = Chinese (Simplified)
+items = { $n ->
+    [one] one item
+   *[other] { $n } items
+}
+user-greeting = hello { $name } from { $address_city }
+user-profile = { $user_name } ({ $user_age })
+user-liked = { $user_name } liked { $gender ->
+    [feminine] her
+    [masculine] his
+   *[other] their
+} own post on { $site_name }
+checkout-title = Checkout
+checkout-form-submit = Pay now
+-product = { $case ->
+   *[nominative] Widget
+    [genitive] Widget's
+}
+-quoted = «{ $text }»
+shout = { UPPER($name) }!
+truncated-title = { TRUNCATE($title, length: 5) }
+signed-in = Signed in as <b>{"{{"}user.name{"}}"}</b>, { $note }
+export-as = Export as { $format }
+two-args = { $a }{ $b }
+folder-of = { $folder }{"{{"}user.name{"}}"}
+nested-message = Nested: {"{{"}fluent "simple"{"}}"}
+rich-greeting = <strong>Hello</strong> <em onclick="x()">{ $name }</em> &amp; <script>alert(1)</script>
+help-intro = Welcome, *{ $name }*!
+    .body =
+        Read the **guide**, { $name }:
+
+        - [Docs](https://example.org/docs "Docs")
+        - [Bad](javascript:alert(1))
+
+        <script>alert(1)</script>
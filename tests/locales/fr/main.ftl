@@ -6,4 +6,15 @@ parameter2 = texte une { $param1 } seconde { $param2 }
 
 email = text avec un EMAIL("example@example.org")
 
+welcome = bienvenue sur { -brand }
+locale = construit pour { LOCALE() }
+
 # no fallback
+price = coûte { NUMBER($amount, style: "currency", currency: "EUR") }
+count = { $n } articles
+published = publié le { DATETIME($when, dateStyle: "long") }
+authors = par { LIST($a, $b, $c) }
+written-by = écrit par { $authors }
+nav-sign-in = Se connecter
+t-b64127e4e4f0b575 = Se déconnecter
+hello-user = Bonjour { $name }
@@ -0,0 +1 @@
+simple = טקסט פשוט
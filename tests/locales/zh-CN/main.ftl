@@ -1,2 +1,4 @@
 exists = 儿
-fallback-zh = 气
\ No newline at end of file
+fallback-zh = 气
+written-by = { $authors }著
+-language-name = 简体中文
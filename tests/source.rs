@@ -0,0 +1,49 @@
+use handlebars::*;
+use handlebars_fluent::source::{FtlText, MemorySource};
+use handlebars_fluent::*;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SOURCES_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+fn source() -> MemorySource {
+    SOURCES_CREATED.fetch_add(1, Ordering::SeqCst);
+    MemorySource::new()
+        .with_resource("en-US", "main.ftl", "hello = Hello { $name }\nbye = Bye")
+        .with_resource("fr", "main.ftl", "hello = Bonjour { $name }")
+        .with_resource("fr", "brand.ftl", "made-by = par { -brand }")
+        .with_metadata("fr", r#"native-name = "Français""#)
+}
+
+simple_loader!(load, source(), "en-US", core: FtlText("-brand = Example"), customizer: |bundle, _lang| {
+    bundle.set_use_isolating(false);
+});
+
+#[test]
+fn test_memory_source() {
+    let loader = load();
+    assert_eq!(
+        loader.native_name(&"fr".parse().unwrap()).as_deref(),
+        Some("Français")
+    );
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(loader)));
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "hello" name="Ann"}} | {{fluent "bye"}}"#,
+                &json!({"lang": "fr"})
+            )
+            .unwrap(),
+        "Bonjour Ann | Bye"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "made-by"}}"#, &json!({"lang": "fr"}))
+            .unwrap(),
+        "par Example"
+    );
+    // Both the resources and the metadata are read from a single evaluation of the source
+    assert_eq!(SOURCES_CREATED.load(Ordering::SeqCst), 1);
+}
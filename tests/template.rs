@@ -1,8 +1,10 @@
 use handlebars::*;
 use handlebars_fluent::*;
 
-simple_loader!(load, "./tests/locales", "en-US", core: "./tests/locales/core.ftl", customizer: |bundle| {
-    bundle.set_use_isolating(false)
+simple_loader!(load, "./tests/locales", "en-US", core: "./tests/locales/core.ftl", locale_core: "./tests/core", customizer: |bundle, lang| {
+    bundle.set_use_isolating(false);
+    let lang = lang.to_string();
+    bundle.add_function("LOCALE", move |_, _| lang.clone().into()).unwrap();
 });
 
 use serde_json::json;
@@ -50,6 +52,18 @@ fn test_english() {
             .unwrap(),
         "this should fall back"
     );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "welcome"}}"#, &data)
+            .unwrap(),
+        "welcome to Handlebars Fluent"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "locale"}}"#, &data)
+            .unwrap(),
+        "built for en-US"
+    );
 }
 
 #[test]
@@ -95,6 +109,37 @@ fn test_french() {
             .unwrap(),
         "this should fall back"
     );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "welcome"}}"#, &data)
+            .unwrap(),
+        "bienvenue sur Fluent pour Handlebars"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "locale"}}"#, &data)
+            .unwrap(),
+        "construit pour fr"
+    );
+}
+
+#[test]
+fn test_region() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "fr-FR"});
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "simple"}}"#, &data)
+            .unwrap(),
+        "texte simple"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "-brand"}}"#, &data)
+            .unwrap(),
+        "Fluent pour Handlebars"
+    );
 }
 
 #[test]
@@ -121,3 +166,879 @@ fn test_chinese() {
         "this should fall back"
     );
 }
+
+#[test]
+fn test_number_options() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "en-US", "big": 9007199254740993u64});
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "count" n=big}}"#, &data)
+            .unwrap(),
+        "9007199254740993 items"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "count" n=3 n-minimumFractionDigits=2}}"#, &data)
+            .unwrap(),
+        "3.00 items"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "rank" n=22 n-type="ordinal"}}"#, &data)
+            .unwrap(),
+        "22nd"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "rank" n=22}}"#, &data)
+            .unwrap(),
+        "22th"
+    );
+}
+
+#[test]
+#[cfg(not(feature = "icu"))]
+fn test_datetime() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "en-US", "created": "2024-05-01T14:30:00+02:00"});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "placed" when=created when-type="datetime"}}"#,
+                &data
+            )
+            .unwrap(),
+        "placed on 2024-05-01T14:30:00+02:00"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "placed" when=created when-type="datetime" when-timeZone="UTC"}}"#,
+                &data
+            )
+            .unwrap(),
+        "placed on 2024-05-01T12:30:00Z"
+    );
+    let data = json!({"lang": "en-US", "created": 0, "timezone": "-05:00"});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "placed" when=created when-type="datetime"}}"#,
+                &data
+            )
+            .unwrap(),
+        "placed on 1969-12-31T19:00:00-05:00"
+    );
+    assert!(handlebars
+        .render_template(
+            r#"{{fluent "placed" when="soon" when-type="datetime"}}"#,
+            &data
+        )
+        .is_err());
+    let data = json!({"lang": "en-US", "created": 0, "timezone": "+aé1"});
+    assert!(handlebars
+        .render_template(
+            r#"{{fluent "placed" when=created when-type="datetime"}}"#,
+            &data
+        )
+        .is_err());
+}
+
+#[test]
+#[cfg(not(feature = "icu"))]
+fn test_list() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({
+        "lang": "en-US",
+        "tags": ["a", "b", "c"],
+        "authors": [{"name": "Ann"}, {"name": "Bob"}, {"name": "Cat"}]
+    });
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "written-by" authors=tags}}"#, &data)
+            .unwrap(),
+        "written by a, b, c"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "written-by" authors=authors authors-field="name"}}"#,
+                &data
+            )
+            .unwrap(),
+        "written by Ann, Bob, Cat"
+    );
+}
+
+#[test]
+fn test_lang_and_dir() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent-lang", Box::new(FluentLangHelper::new(load())));
+    handlebars.register_helper("fluent-dir", Box::new(FluentDirHelper::new(load())));
+    let template = r#"<html lang="{{fluent-lang}}" dir="{{fluent-dir}}">"#;
+    let cases = [
+        ("en-US", r#"<html lang="en-US" dir="ltr">"#),
+        ("fr-FR", r#"<html lang="fr" dir="ltr">"#),
+        ("he-IL", r#"<html lang="he" dir="rtl">"#),
+        ("ar", r#"<html lang="en-US" dir="ltr">"#),
+    ];
+    for (lang, expected) in cases {
+        let data = json!({ "lang": lang });
+        assert_eq!(
+            handlebars.render_template(template, &data).unwrap(),
+            expected
+        );
+    }
+
+    let loader = load();
+    assert_eq!(
+        loader.metadata(&"fr".parse().unwrap()),
+        Some(&LocaleMetadata {
+            native_name: Some("Français".into()),
+            dir: None,
+        })
+    );
+    assert_eq!(loader.metadata(&"zh-CN".parse().unwrap()), None);
+    assert_eq!(loader.direction(&"zh-CN".parse().unwrap()), Direction::Ltr);
+}
+
+#[test]
+fn test_each_locale() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper(
+        "each-fluent-locale",
+        Box::new(FluentLocalesHelper::new(load())),
+    );
+    let template = "{{#each-fluent-locale}}\
+                    {{code}}|{{native_name}}|{{display_name}}|{{dir}}{{#if is_current}}*{{/if}}\
+                    {{#unless @last}}, {{/unless}}\
+                    {{/each-fluent-locale}}";
+    assert_eq!(
+        handlebars
+            .render_template(template, &json!({"lang": "en-US"}))
+            .unwrap(),
+        "en-US|English (US)|English (US)|ltr*, fr|Français|French|ltr, \
+         he|עברית|Hebrew|rtl, zh-CN|简体中文|Chinese (Simplified)|ltr, zh-TW|zh-TW|zh-TW|ltr"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                "{{#each-fluent-locale as |locale|}}{{#if locale.is_current}}{{@index}} {{locale.display_name}}{{/if}}{{/each-fluent-locale}}",
+                &json!({"lang": "fr-CA"})
+            )
+            .unwrap(),
+        "1 Français"
+    );
+}
+
+#[test]
+fn test_register_helpers() {
+    let mut handlebars = Handlebars::new();
+    let names = HelperNames {
+        fluent: "t",
+        param: "tparam",
+        text: "ts",
+        ..Default::default()
+    };
+    register_fluent_helpers(&mut handlebars, load(), names);
+    let data = json!({"lang": "he"});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent-lang}} {{fluent-dir}} {{#t "parameter"}}{{#tparam "param"}}P{{/tparam}}{{/t}} {{ts "Hello"}}"#,
+                &data
+            )
+            .unwrap(),
+        "he rtl text with a P Hello"
+    );
+    assert!(handlebars
+        .render_template(
+            r#"{{#t "parameter"}}{{#fluentparam "param"}}P{{/fluentparam}}{{/t}}"#,
+            &data
+        )
+        .is_err());
+}
+
+#[test]
+fn test_fluentparam() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    let data = json!({"lang": "en-US", "name": "param"});
+
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{#fluent "parameter"}} {{! comment }} {{#fluentparam name}}P{{/fluentparam}} {{/fluent}}"#,
+                &data
+            )
+            .unwrap(),
+        "text with a P"
+    );
+
+    let template = "{{#fluent \"parameter\"}}
+        {{#fluentparam \"param\" dedent=true}}
+            first line
+              second line
+        {{/fluentparam}}
+    {{/fluent}}";
+    assert_eq!(
+        handlebars.render_template(template, &data).unwrap(),
+        "text with a first line\n  second line"
+    );
+
+    let err = handlebars
+        .render_template(r#"{{#fluentparam "param"}}P{{/fluentparam}}"#, &data)
+        .unwrap_err();
+    assert!(err.to_string().contains("inside a {{#fluent}} block"));
+    let err = handlebars
+        .render_template(
+            r#"{{#fluent "parameter"}}stray{{#fluentparam "param"}}P{{/fluentparam}}{{/fluent}}"#,
+            &data,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("\"stray\""));
+    assert!(handlebars
+        .render_template(r#"{{#fluent "parameter"}}{{name}}{{/fluent}}"#, &data)
+        .is_err());
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper(
+        "fluent",
+        Box::new(FluentHelper::new(load()).dedent_params(true)),
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                "{{#fluent \"parameter\"}}{{#fluentparam \"param\"}}\n  P\n{{/fluentparam}}{{/fluent}}",
+                &data
+            )
+            .unwrap(),
+        "text with a P"
+    );
+}
+
+#[test]
+fn test_number_coercion() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    let data = json!({"lang": "en-US", "count": 1, "one": "1", "code": "007"});
+
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{#fluent "items"}}{{#fluentparam "n"}}{{count}}{{/fluentparam}}{{/fluent}}"#,
+                &data
+            )
+            .unwrap(),
+        "1 items"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{#fluent "items"}}{{#fluentparam "n" type="number"}} {{count}} {{/fluentparam}}{{/fluent}}"#,
+                &data
+            )
+            .unwrap(),
+        "one item"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "items" n=one n-type="number"}}"#, &data)
+            .unwrap(),
+        "one item"
+    );
+    assert!(handlebars
+        .render_template(r#"{{fluent "items" n=code n-type="number"}}"#, &data)
+        .is_err());
+    assert!(handlebars
+        .render_template(
+            r#"{{#fluent "items"}}{{#fluentparam "n" type="number"}}many{{/fluentparam}}{{/fluent}}"#,
+            &data
+        )
+        .is_err());
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper(
+        "fluent",
+        Box::new(FluentHelper::new(load()).coerce_numbers(true)),
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "items" n=one}}"#, &data)
+            .unwrap(),
+        "one item"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "items" n=code}}"#, &data)
+            .unwrap(),
+        "007 items"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{#fluent "items"}}{{#fluentparam "n"}}{{count}}{{/fluentparam}}{{/fluent}}"#,
+                &data
+            )
+            .unwrap(),
+        "one item"
+    );
+}
+
+#[test]
+fn test_spread_args() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({
+        "lang": "en-US",
+        "user": {
+            "name": "Ann",
+            "age": 41,
+            "tags": ["a", "b"],
+            "address": {"city": "Paris"},
+            "admin": true
+        }
+    });
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "user-greeting" args=user}}"#, &data)
+            .unwrap(),
+        "hello Ann from Paris"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "user-greeting" args=user name="you" address_city="Lyon"}}"#,
+                &data
+            )
+            .unwrap(),
+        "hello you from Lyon"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "user-profile" args=user args-prefix="user_"}}"#,
+                &data
+            )
+            .unwrap(),
+        "Ann (41)"
+    );
+}
+
+#[test]
+fn test_default_args() {
+    let mut handlebars = Handlebars::new();
+    let helper = FluentHelper::new(load())
+        .with_default_arg("site_name", "Example")
+        .with_context_arg("user_name", "user.name")
+        .with_default_arg("gender", "other")
+        .with_context_arg("gender", "user.gender");
+    handlebars.register_helper("fluent", Box::new(helper));
+    let data = json!({"lang": "en-US", "user": {"name": "Ann", "gender": "feminine"}});
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "user-liked"}}"#, &data)
+            .unwrap(),
+        "Ann liked her own post on Example"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{#fluent "user-liked" site_name="Elsewhere"}}{{#fluentparam "user_name"}}Bob{{/fluentparam}}{{/fluent}}"#,
+                &data
+            )
+            .unwrap(),
+        "Bob liked her own post on Elsewhere"
+    );
+    let data = json!({"lang": "en-US", "user": {"name": "Cat"}});
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "user-liked"}}"#, &data)
+            .unwrap(),
+        "Cat liked their own post on Example"
+    );
+}
+
+#[test]
+fn test_locale_override() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    let data = json!({"lang": "en-US", "other": "fr"});
+    let render = |template: &str| handlebars.render_template(template, &data);
+
+    assert_eq!(
+        render(r#"{{fluent "simple" lang="fr"}} / {{fluent "simple"}}"#).unwrap(),
+        "texte simple / simple text"
+    );
+    assert_eq!(
+        render(r#"{{fluent "parameter" lang=other param="x"}}"#).unwrap(),
+        "texte avec une x"
+    );
+    assert_eq!(
+        render(
+            r#"{{#fluent-locale other}}{{fluent "simple"}} {{fluent-lang}} {{#fluent-locale "he"}}{{fluent-dir}} {{fluent "simple" lang="en-US"}}{{/fluent-locale}} {{fluent-lang}}{{/fluent-locale}} {{fluent-lang}}"#
+        )
+        .unwrap(),
+        "texte simple fr rtl simple text fr en-US"
+    );
+    // Locales without strings fall back like the context's "lang" does
+    assert_eq!(
+        render(
+            r#"{{fluent "simple" lang="de"}} {{#fluent-locale "de"}}{{fluent "-brand"}} {{fluent-lang}}{{/fluent-locale}}"#
+        )
+        .unwrap(),
+        "simple text Handlebars Fluent en-US"
+    );
+    assert!(render(r#"{{fluent "simple" lang="not a language"}}"#).is_err());
+    assert!(render(r#"{{#fluent-locale}}{{/fluent-locale}}"#).is_err());
+}
+
+#[test]
+fn test_lang_resolution() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    handlebars
+        .register_partial("item", r#"{{fluent "simple"}} {{fluent-lang}};"#)
+        .unwrap();
+
+    let data = json!({"items": [{"name": "a"}, {"name": "b"}]});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{*fluent-lang "fr"}}{{#each items}}{{> item this}}{{/each}}"#,
+                &data
+            )
+            .unwrap(),
+        "texte simple fr;texte simple fr;"
+    );
+
+    let data = json!({"lang": "fr", "item": {"name": "a"}});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{*fluent-lang}}{{#with item}}{{> item this}}{{/with}}"#,
+                &data
+            )
+            .unwrap(),
+        "texte simple fr;"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{> item item lang="he"}}"#, &json!({"item": {}}))
+            .unwrap(),
+        "טקסט פשוט he;"
+    );
+
+    assert!(handlebars
+        .render_template(r#"{{fluent "simple"}}"#, &json!({}))
+        .is_err());
+    assert!(handlebars
+        .render_template(r#"{{fluent "simple"}}"#, &json!({"lang": 3}))
+        .is_err());
+    assert!(handlebars
+        .render_template(r#"{{*fluent-lang}}"#, &json!({}))
+        .is_err());
+}
+
+#[test]
+fn test_scopes() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    let data = json!({"lang": "en-US"});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{#fluent-scope "checkout"}}{{fluent ".title"}}: {{#fluent-scope ".form"}}{{fluent ".submit"}}{{/fluent-scope}} {{fluent "simple"}} {{fluent ".title"}}{{/fluent-scope}}"#,
+                &data
+            )
+            .unwrap(),
+        "Checkout: Pay now simple text Checkout"
+    );
+    assert!(handlebars
+        .render_template(r#"{{fluent ".title"}}"#, &data)
+        .is_err());
+    assert!(handlebars
+        .render_template(r#"{{#fluent-scope ".form"}}{{/fluent-scope}}"#, &data)
+        .is_err());
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper(
+        "fluent",
+        Box::new(FluentHelper::new(load()).scope_by_template(true)),
+    );
+    handlebars.register_helper("fluent-scope", Box::new(FluentScopeHelper));
+    handlebars
+        .register_template_string("pages/checkout.hbs", r#"{{fluent ".title"}} {{> form}}"#)
+        .unwrap();
+    handlebars
+        .register_template_string(
+            "form",
+            r#"{{#fluent-scope "checkout-form"}}{{fluent ".submit"}}{{/fluent-scope}}"#,
+        )
+        .unwrap();
+    assert_eq!(
+        handlebars.render("pages/checkout.hbs", &data).unwrap(),
+        "Checkout Pay now"
+    );
+}
+
+#[test]
+fn test_terms() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let render = |template: &str, lang: &str| {
+        let data = json!({"lang": lang, "text": "a \"b\" \\ {c}\nd"});
+        handlebars.render_template(template, &data).unwrap()
+    };
+    assert_eq!(
+        render(r#"{{fluent "-brand"}}"#, "en-US"),
+        "Handlebars Fluent"
+    );
+    assert_eq!(
+        render(r#"{{fluent "-brand"}}"#, "fr"),
+        "Fluent pour Handlebars"
+    );
+    assert_eq!(render(r#"{{fluent "-product"}}"#, "zh-CN"), "Widget");
+    assert_eq!(
+        render(r#"{{fluent "-product" case="genitive"}}"#, "en-US"),
+        "Widget's"
+    );
+    assert_eq!(
+        render(r#"{{{fluent "-quoted" text=text}}}"#, "en-US"),
+        "«a \"b\" \\ {c}\nd»"
+    );
+    assert_eq!(
+        render(r#"{{fluent "-missing"}}"#, "en-US"),
+        "Unknown localization -missing"
+    );
+}
+
+#[test]
+fn test_plural_categories() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    let render = |template: &str, lang: &str| {
+        let data = json!({"lang": lang, "n": 1, "s": "1"});
+        handlebars.render_template(template, &data).unwrap()
+    };
+    let categories = r#"{{fluent-plural 0}} {{fluent-plural n}} {{fluent-plural s}} {{fluent-plural 2}} {{fluent-plural 1.5}}"#;
+    assert_eq!(render(categories, "en-US"), "other one one other other");
+    assert_eq!(render(categories, "fr"), "one one one other one");
+    assert_eq!(render(categories, "he"), "other one one two other");
+    assert_eq!(render(categories, "zh-CN"), "other other other other other");
+    assert_eq!(
+        render(
+            r#"{{fluent-plural 1 type="ordinal"}} {{fluent-plural 2 type="ordinal"}} {{fluent-plural 3 type="ordinal"}} {{fluent-plural 11 type="ordinal"}}"#,
+            "en-US"
+        ),
+        "one two few other"
+    );
+    assert_eq!(
+        render(
+            r#"{{#if (eq (fluent-plural n) "one")}}single{{else}}many{{/if}}"#,
+            "en-US"
+        ),
+        "single"
+    );
+    assert!(handlebars
+        .render_template(r#"{{fluent-plural "many"}}"#, &json!({"lang": "en-US"}))
+        .is_err());
+}
+
+#[test]
+fn test_default_text() {
+    let mut handlebars = Handlebars::new();
+    register_fluent_helpers(&mut handlebars, load(), HelperNames::default());
+    let render = |template: &str, lang: &str| {
+        let data = json!({"lang": lang});
+        handlebars.render_template(template, &data).unwrap()
+    };
+    let template = r#"{{t "Sign in" id="nav-sign-in"}} | {{t "Log out"}} | {{t "Hello" id="hello-user" name="Ann"}} | {{fluent "nav-sign-in" default="Sign in"}} | {{fluent "simple" default="Simple"}}"#;
+    assert_eq!(
+        render(template, "en-US"),
+        "Sign in | Log out | Hello | Sign in | simple text"
+    );
+    assert_eq!(
+        render(template, "fr"),
+        "Se connecter | Se déconnecter | Bonjour Ann | Se connecter | texte simple"
+    );
+    assert_eq!(source_id("Log out"), "t-b64127e4e4f0b575");
+    assert_eq!(
+        render(r#"{{fluent "nav-sign-in"}}"#, "en-US"),
+        "Unknown localization nav-sign-in"
+    );
+    // Defaults are formatted with the same arguments, and apply to terms too
+    assert_eq!(
+        render(
+            r#"{{t "Hello { $name }" name="Ann"}} | {{fluent "nav-sign-in" default="{ $n } items" n=3}} | {{fluent "-brand" default="B"}} | {{fluent "-no-such-term" default="B"}}"#,
+            "en-US"
+        ),
+        "Hello Ann | 3 items | Handlebars Fluent | B"
+    );
+    assert_eq!(
+        render(r#"{{fluent "nav-sign-in" default="Sign in {"}}"#, "en-US"),
+        "Sign in {"
+    );
+    assert_eq!(
+        render(
+            r#"[{{fluent "nav-sign-in" default="  { $n } padded " n=2}}]"#,
+            "en-US"
+        ),
+        "[  2 padded ]"
+    );
+}
+
+#[test]
+fn test_helper_missing() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper(
+        "helperMissing",
+        Box::new(FluentMissingHelper::new(FluentHelper::new(load()))),
+    );
+    let data = json!({"lang": "fr", "simple": "from the context", "user": {"name": "Ann"}});
+    let render =
+        |handlebars: &Handlebars, template: &str| handlebars.render_template(template, &data);
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{parameter param="PARAM"}} | {{hello-user name=user.name}} | {{lang}}"#
+        )
+        .unwrap(),
+        "texte avec une PARAM | Bonjour Ann | fr"
+    );
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{simple}} | {{user.simple}} | {{nothing-here}}"#
+        )
+        .unwrap(),
+        "from the context |  | "
+    );
+    assert!(render(&handlebars, r#"{{nothing-here name="x"}}"#).is_err());
+    // Messages that can't be formatted are left to the usual behaviour, and defaults are used
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{parameter}} | {{nothing-here default="Nothing"}}"#
+        )
+        .unwrap(),
+        " | Nothing"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{simple}}|{{parameter}}"#, &json!({}))
+            .unwrap(),
+        "|"
+    );
+
+    handlebars.set_strict_mode(true);
+    assert_eq!(
+        render(&handlebars, r#"{{hello-user name=user.name}}"#).unwrap(),
+        "Bonjour Ann"
+    );
+    assert!(render(&handlebars, r#"{{parameter2}}"#).is_err());
+    handlebars.set_strict_mode(false);
+
+    handlebars.register_helper(
+        "helperMissing",
+        Box::new(
+            FluentMissingHelper::new(FluentHelper::new(load())).with_fallback(
+                |h: &Helper,
+                 _: &Handlebars,
+                 _: &Context,
+                 _: &mut RenderContext,
+                 out: &mut dyn Output|
+                 -> HelperResult {
+                    out.write(&format!("[{}]", h.name()))?;
+                    Ok(())
+                },
+            ),
+        ),
+    );
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{simple}} {{nothing-here}} {{nothing-here name="x"}} {{user.missing}} {{parameter}}"#
+        )
+        .unwrap(),
+        "from the context [nothing-here] [nothing-here] [user.missing] [parameter]"
+    );
+}
+
+#[test]
+fn test_render_as_template() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper(
+        "fluent",
+        Box::new(FluentHelper::new(load()).render_as_template(true)),
+    );
+    handlebars.register_decorator("fluent-lang", Box::new(FluentLangDecorator));
+    let data = json!({
+        "lang": "en-US",
+        "user": {"name": "A&B"},
+        "note": r"\{{user.name}}",
+        "folder": r"C:\Users\",
+    });
+    let render = |handlebars: &Handlebars, template: &str| {
+        handlebars.render_template(template, &data).unwrap()
+    };
+    assert_eq!(
+        render(&handlebars, r#"{{fluent "signed-in" note="hi"}}"#),
+        "Signed in as <b>A&amp;B</b>, hi"
+    );
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{fluent "signed-in" note="{{user.name}}"}}"#
+        ),
+        "Signed in as <b>A&amp;B</b>, {{user.name}}"
+    );
+    // Backslashes in arguments don't turn an escaped `{{` back into an expression
+    assert_eq!(
+        render(&handlebars, r#"{{fluent "signed-in" note=note}}"#),
+        r"Signed in as <b>A&amp;B</b>, \{{user.name}}"
+    );
+    assert_eq!(
+        render(&handlebars, r#"{{fluent "folder-of" folder=folder}}"#),
+        r"C:\Users\A&amp;B"
+    );
+    // Neither can braces in adjacent arguments, or next to braces in the message
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{fluent "two-args" a="{" b="{user.name}}"}}"#
+        ),
+        "{{user.name}}"
+    );
+    assert_eq!(
+        render(&handlebars, r#"{{fluent "folder-of" folder="{"}}"#),
+        "{A&amp;B"
+    );
+    assert_eq!(
+        render(
+            &handlebars,
+            r#"{{*fluent-lang "fr"}}{{fluent "nested-message"}}"#
+        ),
+        "Nested: texte simple"
+    );
+
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    assert_eq!(
+        render(&handlebars, r#"{{fluent "signed-in" note="hi"}}"#),
+        "Signed in as <b>{{user.name}}</b>, hi"
+    );
+}
+
+#[test]
+fn test_markup_policy() {
+    let render = |helper: FluentHelper<_>| {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("fluent", Box::new(helper));
+        let data = json!({"lang": "en-US", "name": "<b>Ann</b> & co"});
+        handlebars
+            .render_template(
+                r#"{{fluent "rich-greeting" name=name}} {{fluent "nav-sign-in" default="<i>Sign in</i>"}}"#,
+                &data,
+            )
+            .unwrap()
+    };
+    assert_eq!(
+        render(FluentHelper::new(load())),
+        r#"<strong>Hello</strong> <em onclick="x()"><b>Ann</b> & co</em> &amp; <script>alert(1)</script> <i>Sign in</i>"#
+    );
+    assert_eq!(
+        render(FluentHelper::new(load()).with_markup_policy(MarkupPolicy::Escape)),
+        r#"&lt;strong&gt;Hello&lt;/strong&gt; &lt;em onclick="x()"&gt;&lt;b&gt;Ann&lt;/b&gt; &amp; co&lt;/em&gt; &amp; &lt;script&gt;alert(1)&lt;/script&gt; <i>Sign in</i>"#
+    );
+    assert_eq!(
+        render(
+            FluentHelper::new(load())
+                .with_markup_policy(MarkupPolicy::allow(&[("strong", &[]), ("em", &[])]))
+        ),
+        r#"<strong>Hello</strong> <em>&lt;b&gt;Ann&lt;/b&gt; &amp; co</em> &amp; &lt;script&gt;alert(1)&lt;/script&gt; <i>Sign in</i>"#
+    );
+    assert_eq!(
+        render(FluentHelper::new(load()).with_markup_policy(MarkupPolicy::AllowAll)),
+        r#"<strong>Hello</strong> <em onclick="x()">&lt;b&gt;Ann&lt;/b&gt; &amp; co</em> &amp; <script>alert(1)</script> <i>Sign in</i>"#
+    );
+}
+
+#[test]
+fn test_attributes() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "fr", "name": "Ann"});
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "help-intro" name=name}}"#, &data)
+            .unwrap(),
+        "Welcome, *Ann*!"
+    );
+    assert!(handlebars
+        .render_template(r#"{{fluent "help-intro.body" name=name}}"#, &data)
+        .unwrap()
+        .starts_with("Read the **guide**, Ann:\n\n- [Docs]"));
+}
+
+#[test]
+fn test_format_argument() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "en-US"});
+    // Only "markdown" is taken as the output format
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "export-as" format="PDF"}}"#, &data)
+            .unwrap(),
+        "Export as PDF"
+    );
+}
+
+#[cfg(feature = "markdown")]
+#[test]
+fn test_markdown() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    let data = json!({"lang": "fr", "name": "<i>Ann</i>"});
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "help-intro" name=name format="markdown"}}"#,
+                &data
+            )
+            .unwrap(),
+        "<p>Welcome, <em>&lt;i&gt;Ann&lt;/i&gt;</em>!</p>\n"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(r#"{{fluent "help-intro.body" name="Ann" format="markdown"}}"#, &data)
+            .unwrap(),
+        "<p>Read the <strong>guide</strong>, Ann:</p>\n<ul>\n<li><a href=\"https://example.org/docs\" title=\"Docs\">Docs</a></li>\n<li><a>Bad</a></li>\n</ul>\n&lt;script&gt;alert(1)&lt;/script&gt;"
+    );
+    assert_eq!(
+        handlebars
+            .render_template(
+                r#"{{fluent "nav-sign-in" default="**Sign in**" format="markdown" lang="en-US"}}"#,
+                &data
+            )
+            .unwrap(),
+        "<p><strong>Sign in</strong></p>\n"
+    );
+}
+
+#[cfg(not(feature = "markdown"))]
+#[test]
+fn test_markdown_disabled() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_helper("fluent", Box::new(FluentHelper::new(load())));
+    assert!(handlebars
+        .render_template(
+            r#"{{fluent "help-intro" name="Ann" format="markdown"}}"#,
+            &json!({"lang": "en-US"})
+        )
+        .is_err());
+}